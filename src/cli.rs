@@ -0,0 +1,347 @@
+use crate::lexer::{self, Keywords, LexError, Lexer, Span};
+use crate::diagnostic::Diagnostic;
+use crate::messages::{Heading, Lang, Message};
+use crate::parser::{self, SyntaxError, TokenStream};
+use crate::interpreter::{self, RuntimeError};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::rc::Rc;
+
+/// Commands that can be given to lang-01 from the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run {
+        path: String,
+        options: Options,
+    },
+    Repl {
+        options: Options,
+    },
+    Check {
+        path: String,
+        options: Options,
+    },
+    Help,
+}
+
+/// Flags which can be given to run and repl, check only uses keywords and lang
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Options {
+    pub dump_tokens: bool,
+    pub dump_ast: bool,
+    pub trace: bool,
+    pub keywords: Keywords,
+    pub lang: Option<Lang>, // None means the language is taken from the environment
+}
+
+impl Options {
+    fn set(&mut self, flag: &str) -> Result<(), Message> {
+        match flag {
+            "--dump-tokens" => self.dump_tokens = true,
+            "--dump-ast" => self.dump_ast = true,
+            "--trace" => self.trace = true,
+            _ => {
+                if let Some(name) = flag.strip_prefix("--keywords=") {
+                    self.keywords = name.parse()?;
+                }
+                else if let Some(name) = flag.strip_prefix("--lang=") {
+                    self.lang = Some(name.parse()?);
+                }
+                else {
+                    return Err(Message::UnknownFlag(flag.to_string()));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Every error that can stop a script from being run
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: String,
+        error: io::Error,
+    },
+    Lex(Vec<LexError>),
+    Syntax(SyntaxError),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "{}", Message::ReadFile { path: path.to_string(), error: error.to_string() }),
+            Error::Lex(errors) => {
+                let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            },
+            Error::Syntax(error) => write!(f, "{error}"),
+            Error::Runtime(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error {
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Error::Io { path, error } => {
+                let message = Message::ReadFile { path: path.to_string(), error: error.to_string() };
+                vec![Diagnostic::new(&Heading::Error.to_string(), message.to_string(), Span::default()).with_code(message.code())]
+            },
+            Error::Lex(errors) => errors.iter().map(|error| error.diagnostic()).collect(),
+            Error::Syntax(error) => vec![error.diagnostic()],
+            Error::Runtime(error) => vec![error.diagnostic()],
+        }
+    }
+}
+
+impl Command {
+    pub fn options(&self) -> Option<&Options> {
+        match self {
+            Command::Run { options, .. } | Command::Repl { options } | Command::Check { options, .. } => Some(options),
+            Command::Help => None,
+        }
+    }
+}
+
+impl From<Vec<LexError>> for Error {
+    fn from(errors: Vec<LexError>) -> Self {
+        Error::Lex(errors)
+    }
+}
+
+impl From<SyntaxError> for Error {
+    fn from(error: SyntaxError) -> Self {
+        Error::Syntax(error)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
+
+pub const USAGE: &str = "Usage:
+    lang-01 run [flags] <file.mahou>    Runs the given script, use - to read the script from stdin
+    lang-01 repl [flags]                Starts an interactive session
+    lang-01 check [flags] <file.mahou>  Reports syntax errors without running the script
+    lang-01 help                        Shows this message
+
+Flags:
+    --dump-tokens    Prints every token with its line and column
+    --dump-ast       Prints the parsed nodes as a tree
+    --trace          Logs each node as it is interpreted
+    --keywords=<set> Uses english or japanese keywords, japanese also accepts the english ones
+    --lang=<lang>    Shows errors in en or ja, defaults to the language set by LANG";
+
+/// Works out which command was asked for, args should not include the program name
+pub fn parse_args(args: &[String]) -> Result<Command, Message> {
+    let mut args = args.iter();
+    let command = args.next().map(|arg| arg.as_str());
+
+    let mut options = Options::default();
+    let mut positional: Vec<String> = Vec::new();
+    for arg in args {
+        if arg.starts_with("--") {
+            options.set(arg)?;
+        }
+        else {
+            positional.push(arg.to_string());
+        }
+    }
+
+    match command {
+        Some("run") => {
+            let mut positional = positional.into_iter();
+            let path = match positional.next() {
+                Some(path) => path,
+                None => return Err(Message::MissingPath("run")),
+            };
+
+            if let Some(extra) = positional.next() {
+                return Err(Message::UnexpectedArgument(extra.to_string()));
+            }
+
+            Ok(Command::Run { path, options })
+        },
+        Some("repl") => {
+            if let Some(extra) = positional.first() {
+                return Err(Message::UnexpectedArgument(extra.to_string()));
+            }
+
+            Ok(Command::Repl { options })
+        },
+        Some("check") => {
+            let mut positional = positional.into_iter();
+            let path = match positional.next() {
+                Some(path) => path,
+                None => return Err(Message::MissingPath("check")),
+            };
+
+            if let Some(extra) = positional.next() {
+                return Err(Message::UnexpectedArgument(extra.to_string()));
+            }
+
+            Ok(Command::Check { path, options })
+        },
+        Some("help") | Some("-h") | Some("--help") | None => Ok(Command::Help),
+        Some(other) => Err(Message::UnknownCommand(other.to_string())),
+    }
+}
+
+pub fn read_file(path: &str) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|error| Error::Io {
+        path: path.to_string(),
+        error,
+    })
+}
+
+/// Returns every lex and syntax error in the source code in the order they appear, without running anything.
+/// Tokens that failed to lex are left out, so the parser still checks the rest of the file.
+pub fn check_source(input: String, options: &Options) -> Vec<Diagnostic> {
+    let mut tokens: Vec<lexer::Tokens> = Vec::new();
+    let mut errors: Vec<Diagnostic> = Vec::new();
+    for token in Lexer::new(&input).with_keywords(options.keywords) {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error.diagnostic()),
+        }
+    }
+
+    if let Err(syntax_errors) = parser::parse_all(tokens) {
+        errors.extend(syntax_errors.iter().map(|error| error.diagnostic()));
+    }
+
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+/// Lexes, parses and interprets the given source code.
+/// The parser pulls tokens from the lexer as it needs them instead of lexing everything first.
+pub fn run_source(input: String, options: &Options) -> Result<(), Error> {
+    let instructions = parse_source(&input, options)?;
+    interpreter::set_trace(options.trace);
+
+    let mut variables: Vec<interpreter::Variable> = Vec::new();
+    let mut functions: Vec<interpreter::Function> = Vec::new();
+    interpreter::run_program(instructions, &mut variables, &mut functions)?;
+
+    Ok(())
+}
+
+/// How many of the most recent lines run_reader keeps, so memory doesn't grow with the length of the script
+const SOURCE_WINDOW_LINES: usize = 100;
+
+/// The most recent lines read by run_reader, kept so errors can show the line they came from
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceWindow {
+    pub start: usize, // Byte offset of the first line kept
+    pub text: String,
+}
+
+/// Lexes and parses source code while it is being read, so it can be piped in through stdin, then runs it.
+/// The last lines that were read are given back with the result, so an error can show the line it came from.
+pub fn run_reader(reader: impl BufRead, options: &Options) -> (Result<(), Error>, SourceWindow) {
+    let source: Rc<RefCell<Recorded>> = Rc::default();
+    let recorder = Recorder { reader, source: Rc::clone(&source) };
+
+    let result = parse_lexer(Lexer::from_reader(recorder), options).and_then(|instructions| {
+        interpreter::set_trace(options.trace);
+
+        let mut variables: Vec<interpreter::Variable> = Vec::new();
+        let mut functions: Vec<interpreter::Function> = Vec::new();
+        interpreter::run_program(instructions, &mut variables, &mut functions)?;
+        Ok(())
+    });
+
+    let source = source.borrow();
+    (result, SourceWindow { start: source.start, text: String::from_utf8_lossy(&source.bytes).into_owned() })
+}
+
+/// The bytes of the most recent lines that have been read
+#[derive(Default)]
+struct Recorded {
+    start: usize,
+    bytes: Vec<u8>,
+    lines: usize,
+}
+
+impl Recorded {
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+        self.lines += bytes.iter().filter(|byte| **byte == b'\n').count();
+
+        while self.lines > SOURCE_WINDOW_LINES {
+            let Some(end) = self.bytes.iter().position(|byte| *byte == b'\n') else { break };
+            self.bytes.drain(..=end);
+            self.start += end + 1;
+            self.lines -= 1;
+        }
+    }
+}
+
+/// Passes through everything read from the reader while keeping the last lines that were read
+struct Recorder<R> {
+    reader: R,
+    source: Rc<RefCell<Recorded>>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.source.borrow_mut().push(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Recorder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        // The bytes being consumed are still in the reader's buffer until consume is called on it
+        if let Ok(buffer) = self.reader.fill_buf() {
+            self.source.borrow_mut().push(&buffer[..amount.min(buffer.len())]);
+        }
+        self.reader.consume(amount);
+    }
+}
+
+/// Parses the source code while it is being lexed, dumping tokens and nodes if asked to
+pub fn parse_source(input: &str, options: &Options) -> Result<Vec<parser::Node>, Error> {
+    parse_lexer(Lexer::new(input), options)
+}
+
+fn parse_lexer(lexer: Lexer, options: &Options) -> Result<Vec<parser::Node>, Error> {
+    let dump_tokens = options.dump_tokens;
+    let mut tokens = TokenStream::new(lexer.with_keywords(options.keywords).inspect(move |token| match token {
+        Ok(token) if dump_tokens => lexer::dump_token(token),
+        _ => {}
+    }));
+
+    let instructions = parser::parse_stream(&mut tokens);
+    let instructions = match (instructions, tokens.finish()) {
+        (Ok(instructions), Ok(())) => instructions,
+        (Ok(_), Err(lex_errors)) => return Err(Error::Lex(lex_errors)),
+        (Err(error), Ok(())) => return Err(Error::Syntax(error)),
+        // Lex errors before the syntax error are reported instead of it as they are usually what caused it,
+        // lex errors after it are left out so they don't hide it
+        (Err(error), Err(lex_errors)) => {
+            let earlier: Vec<LexError> = lex_errors.into_iter().filter(|lex_error| lex_error.span.start < error.span().start).collect();
+            return Err(if earlier.is_empty() { Error::Syntax(error) } else { Error::Lex(earlier) });
+        },
+    };
+
+    if options.dump_ast {
+        parser::dump_ast(&instructions);
+    }
+
+    Ok(instructions)
+}
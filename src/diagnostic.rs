@@ -0,0 +1,151 @@
+use crate::lexer::Span;
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+#[cfg(test)]
+mod tests;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Extra information attached to another part of the source, e.g. where a block was opened
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Self {
+        Label { span, message }
+    }
+}
+
+/// An error ready to be shown to the user, pointing at where in the source it happened
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub title: String,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn new(title: &str, message: String, span: Span) -> Self {
+        Diagnostic { title: title.to_string(), code: None, message, span, labels: Vec::new() }
+    }
+
+    /// Error codes are shown after the title, e.g. syntax error[E0201]
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Vec<Label>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Formats the diagnostic with the offending source line and a caret under the span.
+    /// A span on line 0 means the location is unknown, so only the message is shown.
+    pub fn render(&self, file_name: &str, source: &str, colour: bool) -> String {
+        self.render_from(file_name, source, 0, colour)
+    }
+
+    /// Same as render, but source only holds the part of the file from the byte offset onwards.
+    /// Spans from before the offset still show where they are, just without the source line.
+    pub fn render_from(&self, file_name: &str, source: &str, offset: usize, colour: bool) -> String {
+        let shift = |span: Span| (span.start >= offset).then(|| Span { start: span.start - offset, end: span.end - offset, ..span });
+        let paint = |code: &str, text: &str| {
+            if colour { format!("{code}{text}{RESET}") } else { text.to_string() }
+        };
+
+        let title = match self.code {
+            Some(code) => format!("{}[{code}]:", self.title),
+            None => format!("{}:", self.title),
+        };
+
+        let mut output = format!("{}{}\n", paint(RED, &title), paint(BOLD, &format!(" {}", self.message)));
+        if self.span.line == 0 {
+            return output;
+        }
+
+        let largest_line = self.labels.iter().map(|label| label.span.line).fold(self.span.line, u64::max);
+        let gutter = " ".repeat(largest_line.to_string().len());
+
+        output.push_str(&format!("{gutter}{} {file_name}:{}:{}\n", paint(BLUE, "-->"), self.span.line, self.span.column));
+        if let Some(span) = shift(self.span) {
+            output.push_str(&format!("{gutter} {}\n", paint(BLUE, "|")));
+            output.push_str(&snippet(source, span, '^', "", &gutter, &|text| paint(RED, text), &|text| paint(BLUE, text)));
+        }
+
+        for label in &self.labels {
+            let Some(span) = shift(label.span).filter(|span| span.line != 0) else {
+                continue;
+            };
+
+            output.push_str(&format!("{gutter} {}\n", paint(BLUE, "|")));
+            output.push_str(&snippet(source, span, '-', &label.message, &gutter, &|text| paint(BLUE, text), &|text| paint(BLUE, text)));
+        }
+
+        output
+    }
+}
+
+/// Formats a single source line with the span underlined using the marker character
+fn snippet(source: &str, span: Span, marker: char, message: &str, gutter: &str, mark: &dyn Fn(&str) -> String, margin: &dyn Fn(&str) -> String) -> String {
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = source[start..].find('\n').map(|i| start + i).unwrap_or(source.len());
+    let end = span.end.clamp(start, line_end);
+
+    let text = source[line_start..line_end].replace('\t', "    ");
+    let padding = width(&source[line_start..start]);
+    let underline = width(&source[start..end]).max(1);
+
+    let number = format!("{:>width$}", span.line, width = gutter.len());
+    let mut underline = marker.to_string().repeat(underline);
+    if !message.is_empty() {
+        underline = format!("{underline} {message}");
+    }
+
+    format!(
+        "{} {text}\n{gutter} {} {}{}\n",
+        margin(&format!("{number} |")),
+        margin("|"),
+        " ".repeat(padding),
+        mark(&underline),
+    )
+}
+
+/// Number of terminal columns the text takes up, CJK characters take up two columns
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| match ch {
+            '\t' => 4,
+            '\u{1100}'..='\u{115F}' | '\u{2E80}'..='\u{A4CF}' | '\u{AC00}'..='\u{D7A3}' |
+            '\u{F900}'..='\u{FAFF}' | '\u{FE30}'..='\u{FE4F}' | '\u{FF00}'..='\u{FF60}' |
+            '\u{FFE0}'..='\u{FFE6}' => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Colour is only used when writing errors to a terminal, and can be turned off with NO_COLOR
+pub fn use_colour() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// Prints the diagnostic to stderr
+pub fn report(diagnostic: &Diagnostic, file_name: &str, source: &str) {
+    report_from(diagnostic, file_name, source, 0);
+}
+
+/// Prints the diagnostic to stderr when only the source from the byte offset onwards was kept
+pub fn report_from(diagnostic: &Diagnostic, file_name: &str, source: &str, offset: usize) {
+    eprint!("{}", diagnostic.render_from(file_name, source, offset, use_colour()));
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+fn create_fake_span(line: u64, column: u64, start: usize, end: usize) -> Span {
+    Span { line, column, start, end }
+}
+
+#[test]
+fn test_render_caret() {
+    let source = "print(\"a\");\nprint(1);\n";
+    let diagnostic = Diagnostic::new("syntax error", "Expected String".to_string(), create_fake_span(2, 7, 18, 19));
+
+    assert_eq!(
+        diagnostic.render("test.mahou", source, false),
+        "syntax error: Expected String\n --> test.mahou:2:7\n  |\n2 | print(1);\n  |       ^\n"
+    );
+}
+
+#[test]
+fn test_render_label() {
+    let source = "repeat(2) {\nprint(\"a\")\n";
+    let diagnostic = Diagnostic::new("syntax error", "Did not close section".to_string(), create_fake_span(3, 1, 23, 23))
+        .with_labels(vec![Label::new(create_fake_span(1, 11, 10, 11), "block opened here".to_string())]);
+
+    assert_eq!(
+        diagnostic.render("test.mahou", source, false),
+        "syntax error: Did not close section\n --> test.mahou:3:1\n  |\n3 | \n  | ^\n  |\n1 | repeat(2) {\n  |           - block opened here\n"
+    );
+}
+
+#[test]
+fn test_wide_characters() {
+    assert_eq!(width("表示("), 5);
+}
+
+#[test]
+fn test_unknown_location() {
+    let diagnostic = Diagnostic::new("error", "Could not read file".to_string(), Span::default());
+
+    assert_eq!(diagnostic.render("test.mahou", "", false), "error: Could not read file\n");
+}
+
+#[test]
+fn test_render_code() {
+    let diagnostic = Diagnostic::new("syntax error", "Unexpected end of file".to_string(), Span::default()).with_code("E0203");
+
+    assert_eq!(diagnostic.render("test.mahou", "", false), "syntax error[E0203]: Unexpected end of file\n");
+}
+
+#[test]
+fn test_render_from_offset() {
+    let source = "print(1);\n";
+    let diagnostic = Diagnostic::new("syntax error", "Expected String".to_string(), create_fake_span(2, 7, 18, 19));
+
+    assert_eq!(
+        diagnostic.render_from("test.mahou", source, 12, false),
+        "syntax error: Expected String\n --> test.mahou:2:7\n  |\n2 | print(1);\n  |       ^\n"
+    );
+    assert_eq!(
+        diagnostic.render_from("test.mahou", source, 20, false),
+        "syntax error: Expected String\n --> test.mahou:2:7\n"
+    );
+}
@@ -1,173 +1,611 @@
-use crate::parser::Node;
+use crate::lexer::Span;
+use crate::diagnostic::Diagnostic;
+use crate::messages::{Heading, Message};
+use crate::parser::{Comparator, Node, NodeKind, Operator, PrintKind};
 
+use std::cell::Cell;
+use std::cmp;
+use std::fmt;
+use std::io::{self, Write};
 use std::iter::Peekable;
+use std::rc::Rc;
 use std::slice::Iter;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[cfg(test)]
 mod tests;
 
 
-#[derive(Debug)]
+/// When enabled every node is logged to stderr as it is interpreted
+static TRACE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_trace(enabled: bool) {
+    TRACE.store(enabled, Ordering::Relaxed);
+}
+
+/// How many calls can be inside of each other before a RuntimeError is raised, instead of overflowing the stack
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+thread_local! {
+    /// How many function calls are running on this thread right now
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, PartialEq)]
 pub struct RuntimeError {
-    message: String
+    message: Message,
+    span: Span
 }
 
 impl RuntimeError {
-    pub fn new(message: String) -> Self {
+    pub fn new(message: Message, span: Span) -> Self {
         RuntimeError {
-            message
+            message,
+            span
+        }
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(&Heading::RuntimeError.to_string(), self.message.to_string(), self.span)
+            .with_code(self.message.code())
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}:{}: {}", Heading::RuntimeError, self.span.line, self.span.column, self.message)
+    }
+}
+
+/// Values are what expressions produce when they are evaluated
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+}
+
+impl Value {
+    /// Name of the type of the value, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::String(_) => "String",
+            Value::Bool(_) => "Bool",
+        }
+    }
+}
+
+impl Value {
+    /// Whether the value counts as true when used as a condition.
+    /// false, 0, 0.0, NaN and "" are false, every other value is true.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(bool) => *bool,
+            Value::Int(num) => *num != 0,
+            Value::Float(num) => *num != 0.0 && !num.is_nan(),
+            Value::String(str) => !str.is_empty(),
         }
     }
 }
 
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(num) => write!(f, "{num}"),
+            Value::Float(num) => write!(f, "{num:?}"),
+            Value::String(str) => write!(f, "{str}"),
+            Value::Bool(bool) => write!(f, "{bool}"),
+        }
+    }
+}
+
+/// A named value, created the first time the name is assigned to
 #[derive(Debug, PartialEq, Clone)]
-pub enum Variable {
-    Int {
-        name: String,
-        num: i64
-    },
-    String {
-        name: String,
-        str: String
-    },
-    Null,
+pub struct Variable {
+    pub name: String,
+    pub value: Value,
 }
 
 impl Variable {
-    // I should work out how to use self but with vectors at some point
-    fn get_variable(variables: Vec<Variable>, find_name: String) -> Result<Variable, RuntimeError> {
-        for variable in variables {
-            match variable {
-                Variable::Int { ref name, num: _  } => {
-                    if *name == find_name {
-                        return Ok(variable);
-                    }
-                },
-                Variable::String { ref name, str: _ } => {
-                    if *name == find_name {
-                        return Ok(variable);
-                    }
-                },
+    pub fn new(name: String, value: Value) -> Self {
+        Variable { name, value }
+    }
 
-                // Error handling
-                Variable::Null => {
-                    return Err(RuntimeError::new(
-                        "Null variable has been saved to variable storage".to_string()
-                    ));
-                }
-            }
+    /// Finds the value of the variable, erroring if it has never been assigned to
+    pub fn get<'a>(variables: &'a [Variable], find_name: &str, span: Span) -> Result<&'a Value, RuntimeError> {
+        variables.iter()
+            .find(|variable| variable.name == find_name)
+            .map(|variable| &variable.value)
+            .ok_or_else(|| RuntimeError::new(Message::UndefinedVariable(find_name.to_string()), span))
+    }
+
+    /// Updates the variable if it exists, otherwise creates it
+    pub fn set(variables: &mut Vec<Variable>, name: &str, value: Value) {
+        match variables.iter_mut().find(|variable| variable.name == name) {
+            Some(variable) => variable.value = value,
+            None => variables.push(Variable::new(name.to_string(), value)),
         }
+    }
 
-        return Ok(Variable::Null);
+    pub fn remove(variables: &mut Vec<Variable>, name: &str) {
+        variables.retain(|variable| variable.name != name);
     }
 }
 
 
+/// A function defined with fn, params are the names its arguments are stored under when it is called.
+/// params and nodes are shared so calling the function doesn't copy its body.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Function {
     name: String,
-    nodes: Vec<Node>,
-    args: Vec<Variable>
+    params: Rc<[String]>,
+    nodes: Rc<[Node]>,
 }
 
 impl Function {
-    fn new(name: String, nodes: Vec<Node>, args: Vec<Variable>) -> Function {
-        return Function { name, nodes, args };
+    pub fn new(name: String, params: Vec<String>, nodes: Vec<Node>) -> Function {
+        Function { name, params: params.into(), nodes: nodes.into() }
     }
 
-    fn null() -> Function {
-        let nodes: Vec<Node> = Vec::new();
-        let args: Vec<Variable> = Vec::new();
-        
-        return Function { name: "null".to_string(), nodes, args}
+    /// Finds the function, erroring if it has never been defined
+    pub fn get<'a>(functions: &'a [Function], find_name: &str, span: Span) -> Result<&'a Function, RuntimeError> {
+        functions.iter()
+            .find(|function| function.name == find_name)
+            .ok_or_else(|| RuntimeError::new(Message::MissingFunction(find_name.to_string()), span))
     }
 
-    fn get_function(functions: Vec<Function>, find_name: String) -> Function {
-        for function in functions {
-            if function.name == find_name {
-                return function;
-            }
+    /// Adds the function, replacing any function with the same name
+    pub fn define(functions: &mut Vec<Function>, function: Function) {
+        match functions.iter_mut().find(|existing| existing.name == function.name) {
+            Some(existing) => *existing = function,
+            None => functions.push(function),
         }
-
-        return Self::null();
     }
+}
 
-    pub fn run_function(mut functions: Vec<Function>, function_name: String) -> Result<(), RuntimeError> {
-        let function: Function = Function::get_function(functions.clone(), function_name);
+/// Calls the function with the arguments, which are worked out using the caller's variables.
+/// The function runs in a fresh frame holding only its parameters, and gives back the value it returned if there was one.
+pub fn call_function(
+    name: &str,
+    args: &[Node],
+    span: Span,
+    variables: &[Variable],
+    functions: &mut Vec<Function>
+) -> Result<Option<Value>, RuntimeError> {
+    let function = Function::get(functions, name, span)?;
+    let (params, nodes) = (Rc::clone(&function.params), Rc::clone(&function.nodes));
+    if args.len() != params.len() {
+        return Err(RuntimeError::new(
+            Message::ArityMismatch { name: name.to_string(), expected: params.len(), found: args.len() },
+            span
+        ));
+    }
 
-        if function.name == "null".to_string() {
-            return Err(RuntimeError::new("Failed to find function".to_string()));
-        }
+    let mut frame: Vec<Variable> = Vec::new();
+    for (param, arg) in params.iter().zip(args) {
+        let value = evaluate(arg, variables, functions)?;
+        Variable::set(&mut frame, param, value);
+    }
 
-        let mut variables: Vec<Variable> = function.args;
-        // variables here would be the arguments passed into the function
-        return Ok(interpret(function.nodes, &mut variables, &mut functions)?);
-    }   
+    let depth = CALL_DEPTH.get();
+    if depth >= MAX_CALL_DEPTH {
+        return Err(RuntimeError::new(Message::RecursionLimit(name.to_string()), span));
+    }
 
-    pub fn create_function(functions: &mut Vec<Function>, function_name: String, function_nodes: Vec<Node>, args: Vec<Variable>) {
-        let function: Function = Function { name: function_name, nodes: function_nodes, args};
+    CALL_DEPTH.set(depth + 1);
+    let flow = run_block(&nodes, &mut frame, functions);
+    CALL_DEPTH.set(depth);
 
-        functions.push(function);
+    match flow? {
+        Flow::Return(value) => Ok(value),
+        _ => Ok(None),
     }
 }
 
 
-// I don't think I need to seperate interpret from interpreter as I do not think I will need to call recursion
+/// How a block of nodes finished running.
+/// break and continue are passed up until they reach their loop, return is passed up until it reaches its function.
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+/// Runs a whole file. Code outside of functions is treated as main,
+/// unless fn main() is defined in which case only main is run.
+pub fn run_program(instructions: Vec<Node>, variables: &mut Vec<Variable>, functions: &mut Vec<Function>) -> Result<(), RuntimeError> {
+    let Some(main) = instructions.iter().find(|node| matches!(&node.kind, NodeKind::DefineFunction { name, .. } if name == "main")) else {
+        return interpret(instructions, variables, functions);
+    };
+
+    // The parser has already checked that nothing but functions are outside of main
+    hoist_functions(&instructions, functions);
+    call_function("main", &[], main.span, variables, functions)?;
+    Ok(())
+}
+
+/// Registers every function defined in the nodes before any of them are run,
+/// so functions can be called before the line they are defined on.
+/// The parser has already checked that no function is defined twice.
+pub fn hoist_functions(instructions: &[Node], functions: &mut Vec<Function>) {
+    for instruction in instructions {
+        if let NodeKind::DefineFunction { name, params, nodes, .. } = &instruction.kind {
+            Function::define(functions, Function::new(name.to_string(), params.to_vec(), nodes.to_vec()));
+        }
+    }
+}
+
 pub fn interpret(instructions: Vec<Node>, variables: &mut Vec<Variable>, functions: &mut Vec<Function>) -> Result<(), RuntimeError> {
+    hoist_functions(&instructions, functions);
+
+    // The parser only allows break, continue and return inside of loops and functions, so the flow here is always Normal
+    run_block(&instructions, variables, functions)?;
+    Ok(())
+}
+
+fn run_block(instructions: &[Node], variables: &mut Vec<Variable>, functions: &mut Vec<Function>) -> Result<Flow, RuntimeError> {
     let iter: Peekable<Iter<Node>> = instructions.iter().peekable();
     
     for instruction in iter {
-        match instruction {
-            // Loops
-            Node::Repeat { count, nodes } => {
-                for _ in 0..*count {
-                    interpret(nodes.to_vec(), variables, functions).unwrap();
+        if TRACE.load(Ordering::Relaxed) {
+            eprintln!("[trace] {}:{} {}", instruction.span.line, instruction.span.column, instruction.kind.label());
+        }
+
+        match &instruction.kind {
+            // Loops, break and continue stop at the loop so only return carries on past it
+            NodeKind::Repeat { .. } | NodeKind::While { .. } | NodeKind::For { .. } => {
+                if let Flow::Return(value) = run_loop(instruction, variables, functions)? {
+                    return Ok(Flow::Return(value));
                 }
             },
+            NodeKind::Break => return Ok(Flow::Break),
+            NodeKind::Continue => return Ok(Flow::Continue),
+            NodeKind::Return { value } => {
+                let value = match value {
+                    Some(value) => Some(evaluate(value, variables, functions)?),
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            },
 
             // Statements
-            Node::SetVariable { var: _ } => {
-                //variables.push(var.clone());
-            },
-            // Run function
-            Node::Function { name, args: _ } => {
-                // Error prone line
-                Function::run_function(functions.to_vec(), name.to_string()).unwrap();
+            NodeKind::SetVariable { name, value } => {
+                let value = evaluate(value, variables, functions)?;
+                Variable::set(variables, name, value);
             },
-            // Define function
-            Node::DefineFunction { name: _, nodes: _, args: _ } => {
-                todo!();
+            // Run function, the value it returns is thrown away
+            NodeKind::Function { name, args } => {
+                call_function(name, args, instruction.span, variables, functions)?;
             },
+            // Define function, the parser only allows functions at the top level so hoist_functions has already defined it
+            NodeKind::DefineFunction { .. } => {},
 
             // Comparisons
-            Node::IfBinaryCompare { comparator: _, lhs: _, rhs: _, nodes: _ } => todo!(),
-            Node::IfUnaryCompare { expected: _, actual: _, nodes: _ } => todo!(),
-            Node::IfElseBinaryCompare { comparator: _, lhs: _, rhs: _, nodes: _ } => todo!(),
-            Node::IfElseUnaryCompare { expected: _, actual: _, nodes: _ } => todo!(),
+            NodeKind::If { branches } => {
+                for branch in branches {
+                    let taken = match &branch.condition {
+                        Some(condition) => evaluate(condition, variables, functions)?.is_truthy(),
+                        None => true,
+                    };
+
+                    if taken {
+                        let flow = run_block(&branch.nodes, variables, functions)?;
+                        if flow != Flow::Normal {
+                            return Ok(flow);
+                        }
+                        break;
+                    }
+                }
+            },
             
+            // Ranges are only parsed as part of a for loop
+            NodeKind::Range { .. } => return Err(RuntimeError::new(Message::NotAnExpression, instruction.span)),
+
             // EOF
-            Node::Eof => return Ok(()),
+            NodeKind::Eof => return Ok(Flow::Normal),
             
             // Built-in functions
-            Node::Print { str } => println!("{str}"),
+            NodeKind::Print { kind, args, sep, end } => {
+                let output = format_print(*kind, args, sep.as_deref(), end.as_deref(), variables, functions)?;
+                if kind.is_stderr() {
+                    eprint!("{output}");
+                }
+                else {
+                    print!("{output}");
+                    // Without a new line the output would sit in the buffer until the next line is printed
+                    let _ = io::stdout().flush();
+                }
+            },
 
-            // Error handling ----------------------------------------------------------------------
-            Node::Else { nodes: _ } => {
-                return Err(RuntimeError::new(
-                    "Else node found in unexpected locaiton".to_string()
-                ));
+            // Expression statements are run for their errors, the value is thrown away
+            NodeKind::Int(_) | NodeKind::Float(_) | NodeKind::String(_) | NodeKind::Bool(_) |
+            NodeKind::Variable { .. } | NodeKind::UnaryExpr { .. } | NodeKind::BinaryExpr { .. } |
+            NodeKind::Compare { .. } => {
+                evaluate(instruction, variables, functions)?;
             },
+        }
+    }
+
+    Ok(Flow::Normal)
+}
+
+
+/// Runs a repeat, while or for loop.
+/// Gives back Flow::Return if the body returned from the function the loop is in, otherwise Flow::Normal.
+fn run_loop(instruction: &Node, variables: &mut Vec<Variable>, functions: &mut Vec<Function>) -> Result<Flow, RuntimeError> {
+    match &instruction.kind {
+        NodeKind::Repeat { count, index, nodes } => {
+            let times = match evaluate(count, variables, functions)? {
+                Value::Int(times) if times < 0 => return Err(RuntimeError::new(Message::NegativeRepeatCount(times), count.span)),
+                Value::Int(times) => times,
+                value => return Err(RuntimeError::new(Message::RepeatCountNotInt(value.type_name()), count.span)),
+            };
+
+            match index {
+                Some(index) => run_scoped(index, variables, |variables| {
+                    for i in 0..times {
+                        Variable::set(variables, index, Value::Int(i));
+                        if let Some(flow) = loop_exit(run_block(nodes, variables, functions)?) {
+                            return Ok(flow);
+                        }
+                    }
+                    Ok(Flow::Normal)
+                }),
+                None => {
+                    for _ in 0..times {
+                        if let Some(flow) = loop_exit(run_block(nodes, variables, functions)?) {
+                            return Ok(flow);
+                        }
+                    }
+                    Ok(Flow::Normal)
+                },
+            }
+        },
+        NodeKind::While { condition, nodes } => {
+            while evaluate(condition, variables, functions)?.is_truthy() {
+                if let Some(flow) = loop_exit(run_block(nodes, variables, functions)?) {
+                    return Ok(flow);
+                }
+            }
+            Ok(Flow::Normal)
+        },
+        NodeKind::For { variable, iterable, nodes } => {
+            run_scoped(variable, variables, |variables| run_for(variable, iterable, nodes, variables, functions))
+        },
+
+        _ => Ok(Flow::Normal),
+    }
+}
+
+/// Works out whether a loop stops after its body has run, giving back the flow the loop ends with if it does
+fn loop_exit(flow: Flow) -> Option<Flow> {
+    match flow {
+        Flow::Normal | Flow::Continue => None,
+        Flow::Break => Some(Flow::Normal),
+        Flow::Return(value) => Some(Flow::Return(value)),
+    }
+}
+
+/// Runs a loop whose variable only exists inside of the loop, the old value of the name comes back afterwards
+fn run_scoped(
+    name: &str,
+    variables: &mut Vec<Variable>,
+    run: impl FnOnce(&mut Vec<Variable>) -> Result<Flow, RuntimeError>
+) -> Result<Flow, RuntimeError> {
+    let previous = variables.iter().find(|variable| variable.name == name).map(|variable| variable.value.clone());
+    let result = run(variables);
+
+    match previous {
+        Some(value) => Variable::set(variables, name, value),
+        None => Variable::remove(variables, name),
+    }
+    result
+}
+
+/// Runs the nodes once for every number in the range, with the number stored in the variable named name
+fn run_for(name: &str, iterable: &Node, nodes: &[Node], variables: &mut Vec<Variable>, functions: &mut Vec<Function>) -> Result<Flow, RuntimeError> {
+    let NodeKind::Range { start, end, inclusive, step } = &iterable.kind else {
+        let value = evaluate(iterable, variables, functions)?;
+        return Err(RuntimeError::new(Message::NotIterable(value.type_name()), iterable.span));
+    };
+
+    let mut current = range_bound(start, variables, functions)?;
+    let end = range_bound(end, variables, functions)?;
+    let step = match step {
+        Some(step) => range_bound(step, variables, functions)?,
+        None => 1,
+    };
+    if step == 0 {
+        return Err(RuntimeError::new(Message::ZeroStep, iterable.span));
+    }
+
+    // A negative step counts down, so the range ends once the number goes below the end
+    loop {
+        let in_range = match (step > 0, *inclusive) {
+            (true, false) => current < end,
+            (true, true) => current <= end,
+            (false, false) => current > end,
+            (false, true) => current >= end,
+        };
+        if !in_range {
+            break;
+        }
+
+        Variable::set(variables, name, Value::Int(current));
+        if let Some(flow) = loop_exit(run_block(nodes, variables, functions)?) {
+            return Ok(flow);
+        }
 
-            Node::Int(_) => todo!(),
-            Node::String(_) => todo!(),
-            Node::Bool(_) => todo!(),
-            Node::UnaryExpr { op: _, child: _ } => todo!(),
-            Node::BinaryExpr { op: _, lhs: _, rhs: _ } => todo!(),
-            Node::CombineStr { lhs: _, rhs: _ } => todo!(),
-            // -------------------------------------------------------------------------------------
+        match current.checked_add(step) {
+            Some(next) => current = next,
+            None => break,
         }
     }
-    
-    return Ok(());
+
+    Ok(Flow::Normal)
+}
+
+/// Works out the text a print statement writes, each value is shown the same way the repl shows it.
+/// Values are separated by a space and the ln versions end with a new line unless sep or end say otherwise.
+fn format_print(
+    kind: PrintKind,
+    args: &[Node],
+    sep: Option<&Node>,
+    end: Option<&Node>,
+    variables: &[Variable],
+    functions: &mut Vec<Function>
+) -> Result<String, RuntimeError> {
+    let sep = match sep {
+        Some(sep) => evaluate(sep, variables, functions)?.to_string(),
+        None => " ".to_string(),
+    };
+    let end = match end {
+        Some(end) => evaluate(end, variables, functions)?.to_string(),
+        None if kind.ends_line() => "\n".to_string(),
+        None => String::new(),
+    };
+
+    let mut output = String::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            output.push_str(&sep);
+        }
+        output.push_str(&evaluate(arg, variables, functions)?.to_string());
+    }
+    output.push_str(&end);
+
+    Ok(output)
+}
+
+fn range_bound(node: &Node, variables: &[Variable], functions: &mut Vec<Function>) -> Result<i64, RuntimeError> {
+    match evaluate(node, variables, functions)? {
+        Value::Int(num) => Ok(num),
+        value => Err(RuntimeError::new(Message::RangeNotInt(value.type_name()), node.span)),
+    }
+}
+
+/// Works out the value of an expression node, variables are the variables that can be read.
+/// functions are needed as a function call can be used as a value.
+pub fn evaluate(node: &Node, variables: &[Variable], functions: &mut Vec<Function>) -> Result<Value, RuntimeError> {
+    match &node.kind {
+        NodeKind::Int(num) => Ok(Value::Int(*num)),
+        NodeKind::Float(num) => Ok(Value::Float(*num)),
+        NodeKind::String(str) => Ok(Value::String(str.to_string())),
+        NodeKind::Bool(bool) => Ok(Value::Bool(*bool)),
+        NodeKind::Variable { name } => Variable::get(variables, name, node.span).cloned(),
+        NodeKind::Function { name, args } => {
+            call_function(name, args, node.span, variables, functions)?
+                .ok_or_else(|| RuntimeError::new(Message::NoReturnValue(name.to_string()), node.span))
+        },
+        NodeKind::BinaryExpr { op, lhs, rhs } => {
+            let lhs = evaluate(lhs, variables, functions)?;
+            let rhs = evaluate(rhs, variables, functions)?;
+            binary_operation(op, lhs, rhs, node.span)
+        },
+        NodeKind::Compare { comparator, lhs, rhs } => {
+            let lhs = evaluate(lhs, variables, functions)?;
+            let rhs = evaluate(rhs, variables, functions)?;
+            compare(comparator, lhs, rhs, node.span)
+        },
+        NodeKind::UnaryExpr { op: Operator::Minus, child } => {
+            match evaluate(child, variables, functions)? {
+                Value::Int(num) => num.checked_neg().map(Value::Int).ok_or_else(|| RuntimeError::new(Message::IntegerOverflow, node.span)),
+                Value::Float(num) => Ok(Value::Float(-num)),
+                value => Err(RuntimeError::new(Message::NegateNonNumber(value.type_name()), node.span)),
+            }
+        },
+
+        _ => Err(RuntimeError::new(Message::NotAnExpression, node.span)),
+    }
 }
 
+/// Applies the operator to both sides, if either side is a float the other side is turned into a float too
+fn binary_operation(op: &Operator, lhs: Value, rhs: Value, span: Span) -> Result<Value, RuntimeError> {
+    match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => {
+            let result = match op {
+                Operator::Plus => lhs.checked_add(rhs),
+                Operator::Minus => lhs.checked_sub(rhs),
+                Operator::Multiply => lhs.checked_mul(rhs),
+                Operator::Divide => {
+                    if rhs == 0 {
+                        return Err(RuntimeError::new(Message::DivideByZero, span));
+                    }
+                    lhs.checked_div(rhs)
+                },
+            };
+
+            result.map(Value::Int).ok_or_else(|| RuntimeError::new(Message::IntegerOverflow, span))
+        },
+        (Value::Int(lhs), Value::Float(rhs)) => float_operation(op, lhs as f64, rhs, span),
+        (Value::Float(lhs), Value::Int(rhs)) => float_operation(op, lhs, rhs as f64, span),
+        (Value::Float(lhs), Value::Float(rhs)) => float_operation(op, lhs, rhs, span),
+        (Value::String(lhs), Value::String(rhs)) if *op == Operator::Plus => Ok(Value::String(lhs + &rhs)),
+
+        (lhs, rhs) => Err(RuntimeError::new(
+            Message::TypeMismatch { op: op.symbol(), lhs: lhs.type_name(), rhs: rhs.type_name() },
+            span
+        )),
+    }
+}
+
+fn float_operation(op: &Operator, lhs: f64, rhs: f64, span: Span) -> Result<Value, RuntimeError> {
+    let result = match op {
+        Operator::Plus => lhs + rhs,
+        Operator::Minus => lhs - rhs,
+        Operator::Multiply => lhs * rhs,
+        Operator::Divide => {
+            if rhs == 0.0 {
+                return Err(RuntimeError::new(Message::DivideByZero, span));
+            }
+            lhs / rhs
+        },
+    };
+
+    Ok(Value::Float(result))
+}
+
+/// Compares two values, numbers can be compared with each other and strings are compared alphabetically.
+/// Bools can only be checked for equality.
+fn compare(comparator: &Comparator, lhs: Value, rhs: Value, span: Span) -> Result<Value, RuntimeError> {
+    let ordering = match (&lhs, &rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => lhs.partial_cmp(rhs),
+        (Value::Int(lhs), Value::Float(rhs)) => (*lhs as f64).partial_cmp(rhs),
+        (Value::Float(lhs), Value::Int(rhs)) => lhs.partial_cmp(&(*rhs as f64)),
+        (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(rhs),
+        (Value::String(lhs), Value::String(rhs)) => lhs.partial_cmp(rhs),
+        (Value::Bool(lhs), Value::Bool(rhs)) if matches!(comparator, Comparator::Equal | Comparator::NotEqual) => lhs.partial_cmp(rhs),
+
+        _ => {
+            return Err(RuntimeError::new(
+                Message::TypeMismatch { op: comparator.symbol(), lhs: lhs.type_name(), rhs: rhs.type_name() },
+                span
+            ));
+        }
+    };
+
+    // ordering is None when NaN is compared, which is only ever not equal
+    let result = match comparator {
+        Comparator::Equal => ordering == Some(cmp::Ordering::Equal),
+        Comparator::NotEqual => ordering != Some(cmp::Ordering::Equal),
+        Comparator::More => ordering == Some(cmp::Ordering::Greater),
+        Comparator::Less => ordering == Some(cmp::Ordering::Less),
+        Comparator::EqualMore => matches!(ordering, Some(cmp::Ordering::Greater | cmp::Ordering::Equal)),
+        Comparator::EqualLess => matches!(ordering, Some(cmp::Ordering::Less | cmp::Ordering::Equal)),
+    };
+
+    Ok(Value::Bool(result))
+}
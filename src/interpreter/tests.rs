@@ -1,5 +1,3 @@
-use crate::lexer;
-
 use super::*;
 
 #[test]
@@ -7,4 +5,246 @@ use super::*;
 fn test_repeat() {
 
     todo!();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_missing_function_is_error() {
+    let span = Span { line: 3, column: 5, start: 20, end: 27 };
+    let instructions = vec![Node::new(NodeKind::Function { name: "missing".to_string(), args: Vec::new() }, span)];
+
+    let mut variables: Vec<Variable> = Vec::new();
+    let mut functions: Vec<Function> = Vec::new();
+    let error = interpret(instructions, &mut variables, &mut functions).unwrap_err();
+    assert_eq!(*error.message(), Message::MissingFunction("missing".to_string()));
+    assert_eq!(error.span(), span);
+}
+
+#[test]
+fn test_set_and_read_variable() {
+    let span = Span::default();
+    let set = |name: &str, value: Node| Node::new(NodeKind::SetVariable { name: name.to_string(), value: Box::new(value) }, span);
+    let read = |name: &str| Node::new(NodeKind::Variable { name: name.to_string() }, span);
+
+    let instructions = vec![
+        set("x", Node::new(NodeKind::Int(2), span)),
+        set("x", Node::new(NodeKind::BinaryExpr { op: Operator::Multiply, lhs: Box::new(read("x")), rhs: Box::new(Node::new(NodeKind::Int(3), span)) }, span)),
+    ];
+
+    let mut variables: Vec<Variable> = Vec::new();
+    let mut functions: Vec<Function> = Vec::new();
+    interpret(instructions, &mut variables, &mut functions).unwrap();
+
+    assert_eq!(variables, vec![Variable::new("x".to_string(), Value::Int(6))]);
+    assert_eq!(evaluate(&read("x"), &variables, &mut functions), Ok(Value::Int(6)));
+}
+
+#[test]
+fn test_undefined_variable_is_error() {
+    let span = Span { line: 1, column: 1, start: 0, end: 1 };
+    let error = evaluate(&Node::new(NodeKind::Variable { name: "y".to_string() }, span), &[], &mut Vec::new()).unwrap_err();
+
+    assert_eq!(*error.message(), Message::UndefinedVariable("y".to_string()));
+    assert_eq!(error.span(), span);
+}
+
+fn evaluate_source(source: &str) -> Result<Value, RuntimeError> {
+    let tokens = crate::lexer::tokenizer(source.to_string()).unwrap();
+    let nodes = crate::parser::parse(tokens).unwrap();
+    evaluate(&nodes[0], &[], &mut Vec::new())
+}
+
+#[test]
+fn test_evaluate_expressions() {
+    assert_eq!(evaluate_source("(1 + 2) * 3 - 8 / 4;"), Ok(Value::Int(7)));
+    assert_eq!(evaluate_source("1 + 0.5;"), Ok(Value::Float(1.5)));
+    assert_eq!(evaluate_source("-(2.5 * 2);"), Ok(Value::Float(-5.0)));
+    assert_eq!(evaluate_source("\"a\" + \"b\" + \"c\";"), Ok(Value::String("abc".to_string())));
+    assert_eq!(evaluate_source("true;"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_evaluate_errors() {
+    let error = evaluate_source("\"a\" + 1;").unwrap_err();
+    assert_eq!(
+        *error.message(),
+        Message::TypeMismatch { op: "+", lhs: "String", rhs: "Int" }
+    );
+    assert_eq!(error.span(), Span { line: 1, column: 1, start: 0, end: 7 });
+
+    assert_eq!(*evaluate_source("-true;").unwrap_err().message(), Message::NegateNonNumber("Bool"));
+    assert_eq!(*evaluate_source("1 / 0;").unwrap_err().message(), Message::DivideByZero);
+    assert_eq!(*evaluate_source("9223372036854775807 + 1;").unwrap_err().message(), Message::IntegerOverflow);
+}
+
+#[test]
+fn test_truthiness() {
+    assert!(Value::Int(-1).is_truthy());
+    assert!(Value::String("a".to_string()).is_truthy());
+    assert!(!Value::Int(0).is_truthy());
+    assert!(!Value::Float(f64::NAN).is_truthy());
+    assert!(!Value::String(String::new()).is_truthy());
+    assert!(!Value::Bool(false).is_truthy());
+}
+
+#[test]
+fn test_evaluate_comparisons() {
+    assert_eq!(evaluate_source("1 + 1 == 2;"), Ok(Value::Bool(true)));
+    assert_eq!(evaluate_source("2 >= 2.5;"), Ok(Value::Bool(false)));
+    assert_eq!(evaluate_source("\"abc\" < \"abd\";"), Ok(Value::Bool(true)));
+    assert_eq!(evaluate_source("true != false;"), Ok(Value::Bool(true)));
+    assert_eq!(
+        *evaluate_source("true < false;").unwrap_err().message(),
+        Message::TypeMismatch { op: "<", lhs: "Bool", rhs: "Bool" }
+    );
+}
+
+fn run_source(source: &str) -> Result<Vec<Variable>, RuntimeError> {
+    let nodes = crate::parser::parse(crate::lexer::tokenizer(source.to_string()).unwrap()).unwrap();
+
+    let mut variables: Vec<Variable> = Vec::new();
+    let mut functions: Vec<Function> = Vec::new();
+    run_program(nodes, &mut variables, &mut functions)?;
+    Ok(variables)
+}
+
+#[test]
+fn test_if_takes_first_truthy_branch() {
+    let source = "x = 0;\nif (x) { y = 1; } else if (\"text\") { y = 2; } else { y = 3; }";
+    let variables = run_source(source).unwrap();
+
+    assert_eq!(Variable::get(&variables, "y", Span::default()), Ok(&Value::Int(2)));
+}
+
+#[test]
+fn test_while_with_break_and_continue() {
+    let source = "i = 0;\nodd = 0;\nwhile (true) {\n  i = i + 1;\n  if (i > 9) { break; }\n  if (i / 2 * 2 == i) { continue; }\n  odd = odd + 1;\n}";
+    let variables = run_source(source).unwrap();
+
+    assert_eq!(Variable::get(&variables, "i", Span::default()), Ok(&Value::Int(10)));
+    assert_eq!(Variable::get(&variables, "odd", Span::default()), Ok(&Value::Int(5)));
+}
+
+#[test]
+fn test_break_only_leaves_inner_loop() {
+    let source = "count = 0;\nrepeat(3) { repeat(5) { count = count + 1; break; } }";
+    let variables = run_source(source).unwrap();
+
+    assert_eq!(Variable::get(&variables, "count", Span::default()), Ok(&Value::Int(3)));
+}
+
+#[test]
+fn test_for_ranges() {
+    let variables = run_source("a = 0;\nfor i in 0..5 { a = a + i; }\nb = 0;\nfor i in 0..=10 step 5 { b = b + i; }\nc = 0;\nfor i in 3..0 step -1 { c = c * 10 + i; }").unwrap();
+
+    assert_eq!(Variable::get(&variables, "a", Span::default()), Ok(&Value::Int(10)));
+    assert_eq!(Variable::get(&variables, "b", Span::default()), Ok(&Value::Int(15)));
+    assert_eq!(Variable::get(&variables, "c", Span::default()), Ok(&Value::Int(321)));
+}
+
+#[test]
+fn test_for_variable_is_scoped_to_loop() {
+    let variables = run_source("i = \"outer\";\nfor i in 0..3 { }\nfor j in 0..3 { }").unwrap();
+
+    assert_eq!(Variable::get(&variables, "i", Span::default()), Ok(&Value::String("outer".to_string())));
+    assert!(Variable::get(&variables, "j", Span::default()).is_err());
+}
+
+#[test]
+fn test_for_errors() {
+    assert_eq!(*run_source("for i in 0..1.5 { }").unwrap_err().message(), Message::RangeNotInt("Float"));
+    assert_eq!(*run_source("for i in 0..3 step 0 { }").unwrap_err().message(), Message::ZeroStep);
+    assert_eq!(*run_source("for c in \"abc\" { }").unwrap_err().message(), Message::NotIterable("String"));
+}
+
+#[test]
+fn test_repeat_count_expression() {
+    let variables = run_source("n = 2;\ntotal = 0;\nrepeat(n * 2) as i { total = total * 10 + i; }").unwrap();
+
+    assert_eq!(Variable::get(&variables, "total", Span::default()), Ok(&Value::Int(123)));
+    assert!(Variable::get(&variables, "i", Span::default()).is_err());
+}
+
+#[test]
+fn test_repeat_count_errors() {
+    assert_eq!(*run_source("repeat(1 - 3) { }").unwrap_err().message(), Message::NegativeRepeatCount(-2));
+    assert_eq!(*run_source("repeat(1.5) { }").unwrap_err().message(), Message::RepeatCountNotInt("Float"));
+}
+
+#[test]
+fn test_function_call_and_return() {
+    let source = "fn add(a, b) { return a + b; }\nfn first_even(limit) { for i in 1..limit { if (i / 2 * 2 == i) { return i; } } }\nx = add(2, add(3, 4)) * 2;\ny = first_even(10);";
+    let variables = run_source(source).unwrap();
+
+    assert_eq!(Variable::get(&variables, "x", Span::default()), Ok(&Value::Int(18)));
+    assert_eq!(Variable::get(&variables, "y", Span::default()), Ok(&Value::Int(2)));
+}
+
+#[test]
+fn test_function_frame_is_fresh() {
+    let error = run_source("a = 1;\nfn read() { return a; }\nread();").unwrap_err();
+    assert_eq!(*error.message(), Message::UndefinedVariable("a".to_string()));
+
+    let variables = run_source("a = 1;\nfn set(a) { a = 5; }\nset(2);").unwrap();
+    assert_eq!(Variable::get(&variables, "a", Span::default()), Ok(&Value::Int(1)));
+}
+
+#[test]
+fn test_function_errors() {
+    let error = run_source("fn add(a, b) { return a + b; }\nadd(1);").unwrap_err();
+    assert_eq!(*error.message(), Message::ArityMismatch { name: "add".to_string(), expected: 2, found: 1 });
+    assert_eq!(error.span(), Span { line: 2, column: 1, start: 31, end: 37 });
+
+    let error = run_source("fn nothing() { return; }\nx = nothing();").unwrap_err();
+    assert_eq!(*error.message(), Message::NoReturnValue("nothing".to_string()));
+}
+
+#[test]
+fn test_functions_are_hoisted() {
+    let variables = run_source("x = square(3);\nfn square(n) { return n * n; }").unwrap();
+
+    assert_eq!(Variable::get(&variables, "x", Span::default()), Ok(&Value::Int(9)));
+}
+
+#[test]
+fn test_explicit_main() {
+    // main runs in its own frame, so nothing is left in the top level variables
+    assert_eq!(run_source("fn main() { x = helper(); }\nfn helper() { return 1; }"), Ok(Vec::new()));
+    assert_eq!(run_source("x = 1;"), Ok(vec![Variable::new("x".to_string(), Value::Int(1))]));
+}
+
+#[test]
+fn test_format_print() {
+    let format = |source: &str| {
+        let nodes = crate::parser::parse(crate::lexer::tokenizer(source.to_string()).unwrap()).unwrap();
+        let NodeKind::Print { kind, args, sep, end } = &nodes[0].kind else {
+            panic!("expected a print statement");
+        };
+        format_print(*kind, args, sep.as_deref(), end.as_deref(), &[Variable::new("x".to_string(), Value::Int(3))], &mut Vec::new())
+    };
+
+    assert_eq!(format("print(1, 2.0, true, \"a\" + \"b\");"), Ok("1 2.0 true ab".to_string()));
+    assert_eq!(format("println(x * 2, x, sep = \", \");"), Ok("6, 3\n".to_string()));
+    assert_eq!(format("eprintln(x, end = \"!\");"), Ok("3!".to_string()));
+    assert_eq!(format("println();"), Ok("\n".to_string()));
+    assert_eq!(*format("print(y);").unwrap_err().message(), Message::UndefinedVariable("y".to_string()));
+}
+
+#[test]
+fn test_recursion_limit() {
+    // The default test thread stack is too small to reach the limit, so this uses the same stack size as the binary
+    let run = |source: &'static str| {
+        std::thread::Builder::new()
+            .stack_size(crate::STACK_SIZE)
+            .spawn(move || run_source(source).unwrap_err())
+            .unwrap()
+            .join()
+            .unwrap()
+    };
+
+    let error = run("fn f(n) { return f(n + 1); }\nf(1);");
+    assert_eq!(*error.message(), Message::RecursionLimit("f".to_string()));
+    assert_eq!(error.span().line, 1);
+
+    let error = run("fn f(n) {\n  for i in 0..1 { repeat(1) { if (true) { while (true) { return 1 + (2 * f(n + 1)); } } } }\n}\nf(1);");
+    assert_eq!(*error.message(), Message::RecursionLimit("f".to_string()));
+}
@@ -1,10 +1,22 @@
+use crate::diagnostic::{Diagnostic, Label};
+use crate::messages::{Heading, Message, Note};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
 use std::iter::{self, from_fn};
+use std::str::FromStr;
+
+#[cfg(test)]
+mod tests;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Number(i64),
+    Number(u64), // Kept unsigned so the parser can apply a minus to i64::MIN
+    Float(f64),
     String(String),
-    Other(String),
+    Ident(String),
+    DocComment(String),
     Bool(bool),
     Plus,
     Dash,
@@ -15,15 +27,22 @@ pub enum Token {
     LeftBracket,
     RightBracket,
     Dot,
+    DotDot,
+    DotDotEqual,
     Comma,
     Semicolon,
     While,
+    Break,
+    Continue,
     For,
+    In,
     Repeat,
     If,
     Elseif,
     Else,
     Equal,
+    DoubleEqual,
+    Not,
     NotEqual,
     More,
     Less,
@@ -31,168 +50,792 @@ pub enum Token {
     EqualLess,
     Comparator,
     Function,
+    Return,
     Print,
+    Println,
+    Eprint,
+    Eprintln,
     Eof,
 }
 
-#[derive(Clone)]
+/// Which words are lexed as keywords, Japanese also accepts every English keyword.
+/// Set for the whole file with --keywords=japanese or from a line with #pragma keywords japanese.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Keywords {
+    #[default]
+    English,
+    Japanese,
+}
+
+const KEYWORDS: &[(&str, Token)] = &[
+    ("true", Token::Bool(true)),
+    ("false", Token::Bool(false)),
+    ("while", Token::While),
+    ("break", Token::Break),
+    ("continue", Token::Continue),
+    ("for", Token::For),
+    ("in", Token::In),
+    ("repeat", Token::Repeat),
+    ("if", Token::If),
+    ("else", Token::Else),
+    ("fn", Token::Function),
+    ("func", Token::Function),
+    ("function", Token::Function),
+    ("return", Token::Return),
+    ("print", Token::Print),
+    ("println", Token::Println),
+    ("eprint", Token::Eprint),
+    ("eprintln", Token::Eprintln),
+];
+
+const JAPANESE_KEYWORDS: &[(&str, Token)] = &[
+    ("真", Token::Bool(true)),
+    ("偽", Token::Bool(false)),
+    ("もし", Token::If),
+    ("それ以外", Token::Else),
+    ("繰り返す", Token::Repeat),
+    ("関数", Token::Function),
+    ("表示", Token::Print),
+];
+
+impl Keywords {
+    /// Returns the keyword token for the word, or None if it is an identifier
+    fn lookup(self, word: &str) -> Option<Token> {
+        let aliases = match self {
+            Keywords::English => &[],
+            Keywords::Japanese => JAPANESE_KEYWORDS,
+        };
+
+        KEYWORDS.iter()
+            .chain(aliases)
+            .find(|(keyword, _)| *keyword == word)
+            .map(|(_, token)| token.clone())
+    }
+
+    /// Returns the longest Japanese keyword the text starting with first begins with.
+    /// Japanese is written without spaces, so these keywords are found even with other text straight after them.
+    fn japanese_prefix(self, first: char, mut peek: impl FnMut(usize) -> Option<char>) -> Option<(&'static str, Token)> {
+        if self != Keywords::Japanese {
+            return None;
+        }
+
+        JAPANESE_KEYWORDS.iter()
+            .filter(|(keyword, _)| {
+                let mut chars = keyword.chars();
+                chars.next() == Some(first) && chars.enumerate().all(|(i, ch)| peek(i) == Some(ch))
+            })
+            .max_by_key(|(keyword, _)| keyword.len())
+            .map(|(keyword, token)| (*keyword, token.clone()))
+    }
+}
+
+impl FromStr for Keywords {
+    type Err = Message;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "english" | "en" => Ok(Keywords::English),
+            "japanese" | "ja" | "日本語" => Ok(Keywords::Japanese),
+            _ => Err(Message::UnknownKeywords(name.to_string())),
+        }
+    }
+}
+
+/// Where in the source code a token or node comes from
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub line: u64,
+    pub column: u64,
+    pub start: usize, // byte offset of the first character
+    pub end: usize,   // byte offset after the last character
+}
+
+impl Span {
+    /// Joins two spans together, keeping the start of this span and the end of the other
+    pub fn to(self, other: Span) -> Span {
+        Span { end: self.end.max(other.end), ..self }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape(char),
+    InvalidUnicodeEscape,
+    InvalidNumber,
+    NumberTooLarge,
+    InvalidCharacter(char),
+    InvalidPragma(String),
+    Io(String),
+}
+
+/// Errors found while turning the source code into tokens
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+    labels: Vec<Label>,
+}
+
+impl LexError {
+    pub fn new(kind: LexErrorKind, span: Span) -> Self {
+        LexError { kind, span, labels: Vec::new() }
+    }
+
+    /// Points at another part of the source that helps explain the error
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    /// The message in the catalogue for this kind of error
+    pub fn message(&self) -> Message {
+        match &self.kind {
+            LexErrorKind::UnterminatedString => Message::UnterminatedString,
+            LexErrorKind::UnterminatedComment => Message::UnterminatedComment,
+            LexErrorKind::InvalidEscape(ch) => Message::InvalidEscape(*ch),
+            LexErrorKind::InvalidUnicodeEscape => Message::InvalidUnicodeEscape,
+            LexErrorKind::InvalidNumber => Message::InvalidNumber,
+            LexErrorKind::InvalidCharacter(ch) => Message::InvalidCharacter(*ch),
+            LexErrorKind::NumberTooLarge => Message::NumberTooLarge,
+            LexErrorKind::InvalidPragma(pragma) => Message::InvalidPragma(pragma.to_string()),
+            LexErrorKind::Io(error) => Message::Io(error.to_string()),
+        }
+    }
+
+    /// True if the error would go away with more input, used by the repl to keep reading lines
+    pub fn is_unterminated(&self) -> bool {
+        matches!(self.kind, LexErrorKind::UnterminatedString | LexErrorKind::UnterminatedComment)
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let message = self.message();
+        Diagnostic::new(&Heading::SyntaxError.to_string(), message.to_string(), self.span)
+            .with_code(message.code())
+            .with_labels(self.labels.clone())
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}:{}: {}", Heading::SyntaxError, self.span.line, self.span.column, self.message())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Tokens {
     pub token: Token,
-    pub line: u64 // currently just implementing line number for errors, may include character number later
+    pub span: Span,
 }
 
 impl Tokens {
-    pub fn new(token: Token, line: u64) -> Self {
-        Self { token, line }
+    pub fn new(token: Token, span: Span) -> Self {
+        Self { token, span }
     }
 }
 
+/// Wraps the characters of the input to keep track of where in the source the lexer is
+struct Cursor<'a> {
+    chars: Box<dyn Iterator<Item = io::Result<char>> + 'a>,
+    lookahead: VecDeque<char>, // Characters that have been peeked but not taken yet
+    error: Option<io::Error>,
+    done: bool,
+    line: u64,
+    column: u64,
+    offset: usize,
+}
 
-pub fn tokenizer(input: String) -> Vec<Tokens> {
-    let mut tokens: Vec<Tokens> = Vec::new();
-    let mut iter = input.chars().peekable();
-    let mut line: u64 = 1;
+impl<'a> Cursor<'a> {
+    fn new(chars: impl Iterator<Item = io::Result<char>> + 'a) -> Self {
+        Cursor {
+            chars: Box::new(chars),
+            lookahead: VecDeque::new(),
+            error: None,
+            done: false,
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
 
-    while let Some(ch) = iter.next() {
-        // pattern matching logic
-        match ch {
-            ch if ch.is_whitespace() => continue,
-            '\n' => line += 1,
-            '(' => tokens.push(Tokens::new(Token::LeftParen, line)),
-            ')' => tokens.push(Tokens::new(Token::RightParen, line)),
-            '{' => tokens.push(Tokens::new(Token::LeftBracket, line)),
-            '}' => tokens.push(Tokens::new(Token::RightBracket, line)),
-            '+' => tokens.push(Tokens::new(Token::Plus, line)),
-            '-' => tokens.push(Tokens::new(Token::Dash, line)),
-            '*' => tokens.push(Tokens::new(Token::Star, line)),
-            '/' => tokens.push(Tokens::new(Token::Slash, line)),
-            '1'..='9' => {
-                let n: i64 = iter::once(ch)
-                    .chain(
-                        from_fn(
-                            || iter.by_ref().next_if(|s| s.is_ascii_digit())
-                        )
-                    )
-                    .collect::<String>()
-                    .parse()
-                    .unwrap();
-
-                tokens.push(Tokens::new(Token::Number(n), line));
-            },
-            '"' => {
-                let s: String = iter::once(ch)
-                    .chain(
-                        from_fn(
-                            || iter.by_ref().next_if(|s| *s != '"')
-                        )
-                    )
-                    .collect::<String>();
+    /// Reads characters until there are more than n in the lookahead, returns false if the input runs out first.
+    /// Reading stops after an io error, which is kept so the lexer can report it.
+    fn fill(&mut self, n: usize) -> bool {
+        while self.lookahead.len() <= n {
+            if self.done {
+                return false;
+            }
 
+            match self.chars.next() {
+                Some(Ok(ch)) => self.lookahead.push_back(ch),
+                Some(Err(error)) => {
+                    self.error = Some(error);
+                    self.done = true;
+                },
+                None => self.done = true,
+            }
+        }
 
-                // Cuts off the first character because im stupid and don't understand how iterators work
-                let mut s = s.chars();
-                s.next();
-                let s = s.as_str().to_string();
+        true
+    }
 
-                tokens.push(Tokens::new(Token::String(s), line));
+    fn next(&mut self) -> Option<char> {
+        if !self.fill(0) {
+            return None;
+        }
+        let ch = self.lookahead.pop_front()?;
 
-                // Moves iter to next location because again im stupid and don't understand how iterators work
-                iter.next();
-            },
-            '。' | '.' => tokens.push(Tokens::new(Token::Dot, line)),
-            ';' | '；' => tokens.push(Tokens::new(Token::Semicolon, line)),
-            ',' | '、' => tokens.push(Tokens::new(Token::Comma, line)),
-            '=' => tokens.push(Tokens::new(Token::Equal, line)),
-
-            _ => {
-                // All multicharacter tokens fall under here
-                let s: String = iter::once(ch)
-                .chain(
-                    from_fn(
-                        || iter.by_ref().next_if(|s| !is_reserved(*s))
-                    )
-                )
-                .collect::<String>();
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+        else {
+            self.column += 1;
+        }
 
-                match s.as_str() {
-                    "true" => {
-                        tokens.push(Tokens::new(Token::Bool(true), line));
-                    }
-                    "false" => {
-                        tokens.push(Tokens::new(Token::Bool(false), line));
-                    }
-                    "while" => {
-                        tokens.push(Tokens::new(Token::While, line));
-                    }
-                    "repeat" => {
-                        tokens.push(Tokens::new(Token::Repeat, line));
-                    }
-                    "if" => {
-                        todo!();
-                    }
-                    "else" => {
-                        // if else would be implemented here
-                        todo!();
-                    }
-                    "fn" | "func" | "function" => {
-                        tokens.push(Tokens::new(Token::Function, line));
-                    }
-                    "print" => {
-                        tokens.push(Tokens::new(Token::Print, line))
-                    }
+        Some(ch)
+    }
+
+    fn next_if(&mut self, func: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.peek_nth(0) {
+            Some(ch) if func(&ch) => self.next(),
+            _ => None,
+        }
+    }
+
+    /// Looks n characters ahead without moving the cursor, 0 is the next character
+    fn peek_nth(&mut self, n: usize) -> Option<char> {
+        self.fill(n);
+        self.lookahead.get(n).copied()
+    }
+
+    /// Empty span at the current position
+    fn position(&self) -> Span {
+        Span { line: self.line, column: self.column, start: self.offset, end: self.offset }
+    }
+
+    /// Span from the given start to the current position
+    fn span_from(&self, start: Span) -> Span {
+        Span { end: self.offset, ..start }
+    }
+}
+
+/// Reads characters from a reader a line at a time
+struct ReadChars<R> {
+    reader: R,
+    line: std::vec::IntoIter<char>,
+}
+
+impl<R: BufRead> Iterator for ReadChars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(ch) = self.line.next() {
+                return Some(Ok(ch));
+            }
+
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => self.line = line.chars().collect::<Vec<char>>().into_iter(),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Lexes the source code one token at a time, so only the tokens being parsed have to be kept in memory.
+/// Errors are returned in place of the token they were found in and lexing carries on after them.
+/// The last token is always Eof.
+pub struct Lexer<'a> {
+    iter: Cursor<'a>,
+    keywords: Keywords,
+    peeked: Option<Result<Tokens, LexError>>, // Lexed early while checking for else if
+    finished: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer { iter: Cursor::new(input.chars().map(Ok)), keywords: Keywords::default(), peeked: None, finished: false }
+    }
+
+    /// Lexes source code read from the reader, e.g. stdin
+    pub fn from_reader(reader: impl BufRead + 'a) -> Self {
+        let chars = ReadChars { reader, line: Vec::new().into_iter() };
+        Lexer { iter: Cursor::new(chars), keywords: Keywords::default(), peeked: None, finished: false }
+    }
+
+    /// Sets the keywords used until a pragma changes them
+    pub fn with_keywords(mut self, keywords: Keywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Lexes the next token, skipping whitespace and comments
+    fn lex_next(&mut self) -> Option<Result<Tokens, LexError>> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let start = self.iter.position();
+            let Some(ch) = self.iter.next() else {
+                if let Some(error) = self.iter.error.take() {
+                    return Some(Err(LexError::new(LexErrorKind::Io(error.to_string()), start)));
+                }
+
+                self.finished = true;
+                return Some(Ok(Tokens::new(Token::Eof, start)));
+            };
+
+            match lex_token(&mut self.iter, ch, start, &mut self.keywords) {
+                Ok(Some(token)) => return Some(Ok(Tokens::new(token, self.iter.span_from(start)))),
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Tokens, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut item = match self.peeked.take() {
+            Some(item) => item,
+            None => self.lex_next()?,
+        };
+
+        // else if is lexed as a single token
+        if let Ok(token) = &mut item {
+            if token.token == Token::Else {
+                match self.lex_next() {
+                    Some(Ok(next)) if next.token == Token::If => {
+                        token.token = Token::Elseif;
+                        token.span = token.span.to(next.span);
+                    },
+                    next => self.peeked = next,
+                }
+            }
+        }
+
+        Some(item)
+    }
+}
+
+/// Turns the source code into tokens.
+/// Lexing carries on after an error so every error in the source is returned at once.
+pub fn tokenizer(input: String) -> Result<Vec<Tokens>, Vec<LexError>> {
+    let mut tokens: Vec<Tokens> = Vec::new();
+    let mut errors: Vec<LexError> = Vec::new();
 
-                    
-                    _ => {
-                        tokens.push(Tokens::new(Token::Other(s), line));
+    for result in Lexer::new(&input) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(tokens)
+    }
+    else {
+        Err(errors)
+    }
+}
+
+/// Lexes the token starting with ch, returning None for whitespace and comments.
+/// keywords is changed when a pragma is found.
+fn lex_token(iter: &mut Cursor, ch: char, start: Span, keywords: &mut Keywords) -> Result<Option<Token>, LexError> {
+    let token = match ch {
+        ch if ch.is_whitespace() => return Ok(None),
+        '(' => Token::LeftParen,
+        ')' => Token::RightParen,
+        '{' => Token::LeftBracket,
+        '}' => Token::RightBracket,
+        '+' => Token::Plus,
+        '-' => Token::Dash,
+        '*' => Token::Star,
+        '/' => Token::Slash,
+        '0'..='9' | '０'..='９' => lex_number(iter, ch, start)?,
+        '"' => {
+            if iter.next_if(|s| *s == '"').is_none() {
+                Token::String(lex_string(iter, '"', start)?)
+            }
+            // Three quotes start a raw string
+            else if iter.next_if(|s| *s == '"').is_some() {
+                Token::String(lex_raw_string(iter, start)?)
+            }
+            else {
+                Token::String(String::new())
+            }
+        },
+        '“' => Token::String(lex_string(iter, '”', start)?),
+        '‘' => Token::String(lex_string(iter, '’', start)?),
+        '「' => Token::String(lex_string(iter, '」', start)?),
+        '『' => Token::String(lex_string(iter, '』', start)?),
+        '.' if iter.next_if(|s| *s == '.').is_some() => {
+            if iter.next_if(|s| *s == '=').is_some() {
+                Token::DotDotEqual
+            }
+            else {
+                Token::DotDot
+            }
+        },
+        '。' | '.' => Token::Dot,
+        ';' | '；' => Token::Semicolon,
+        ',' | '、' => Token::Comma,
+        '=' => {
+            if iter.next_if(|s| *s == '=').is_some() {
+                Token::DoubleEqual
+            }
+            else if iter.next_if(|s| *s == '<').is_some() {
+                Token::EqualLess
+            }
+            else if iter.next_if(|s| *s == '>').is_some() {
+                Token::EqualMore
+            }
+            else {
+                Token::Equal
+            }
+        },
+        '!' => {
+            if iter.next_if(|s| *s == '=').is_some() {
+                Token::NotEqual
+            }
+            else {
+                Token::Not
+            }
+        },
+        '<' => {
+            if iter.next_if(|s| *s == '=').is_some() {
+                Token::EqualLess
+            }
+            else {
+                Token::Less
+            }
+        },
+        '>' => {
+            if iter.next_if(|s| *s == '=').is_some() {
+                Token::EqualMore
+            }
+            else {
+                Token::More
+            }
+        },
+        '#' => {
+            if iter.next_if(|s| *s == '*').is_some() {
+                // Block comments run until *#
+                let mut previous = ' ';
+                loop {
+                    match iter.next() {
+                        Some('#') if previous == '*' => break,
+                        Some(s) => previous = s,
+                        None => return Err(LexError::new(LexErrorKind::UnterminatedComment, iter.span_from(start))),
                     }
                 }
+                return Ok(None);
+            }
+            else if iter.next_if(|s| *s == '#').is_some() {
+                // Doc comments are kept so they can be attached to what comes after them
+                let s: String = from_fn(|| iter.next_if(|s| *s != '\n')).collect();
+                Token::DocComment(s.trim().to_string())
+            }
+            else {
+                let s: String = from_fn(|| iter.next_if(|s| *s != '\n')).collect();
+                if let Some(pragma) = s.strip_prefix("pragma ") {
+                    *keywords = lex_pragma(pragma)
+                        .ok_or_else(|| LexError::new(LexErrorKind::InvalidPragma(pragma.trim().to_string()), iter.span_from(start)))?;
+                }
+                return Ok(None);
+            }
+        },
+
+        ch if is_ident_start(ch) => {
+            if let Some((keyword, token)) = keywords.japanese_prefix(ch, |n| iter.peek_nth(n)) {
+                for _ in keyword.chars().skip(1) {
+                    iter.next();
+                }
+                return Ok(Some(token));
             }
+
+            // Identifiers and keywords
+            let s: String = iter::once(ch)
+            .chain(
+                from_fn(
+                    || iter.next_if(|s| is_ident_continue(*s))
+                )
+            )
+            .collect::<String>();
+
+            match keywords.lookup(&s) {
+                Some(keyword) => keyword,
+                None => Token::Ident(s),
+            }
+        },
+        _ => {
+            return Err(LexError::new(LexErrorKind::InvalidCharacter(ch), iter.span_from(start)));
         }
+    };
+
+    Ok(Some(token))
+}
+
+/// Reads the keywords a #pragma line asks for, the only pragma is keywords followed by english or japanese
+fn lex_pragma(pragma: &str) -> Option<Keywords> {
+    match pragma.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["keywords", name] => name.parse().ok(),
+        _ => None,
     }
+}
 
-    tokens.push(Tokens::new(Token::Eof, line));
+/// Reads a number literal, ch is its first digit.
+/// Supports 0x, 0b and 0o prefixes, _ separators, floats with a fraction or exponent and full width digits.
+fn lex_number(iter: &mut Cursor, ch: char, start: Span) -> Result<Token, LexError> {
+    let first = to_ascii_digit(ch);
 
-    tokens
+    if first == '0' {
+        let radix = match iter.next_if(|s| matches!(s, 'x' | 'X' | 'b' | 'B' | 'o' | 'O')) {
+            Some('x' | 'X') => Some(16),
+            Some('b' | 'B') => Some(2),
+            Some('o' | 'O') => Some(8),
+            _ => None,
+        };
+
+        if let Some(radix) = radix {
+            let digits: String = from_fn(|| iter.next_if(|s| s.is_digit(radix) || *s == '_'))
+                .filter(|s| *s != '_')
+                .collect();
+
+            // Letters straight after the digits such as 0b102 or 0xfg are not valid
+            if digits.is_empty() || iter.peek_nth(0).is_some_and(|s| s.is_alphanumeric()) {
+                while iter.next_if(|s| s.is_alphanumeric() || *s == '_').is_some() {}
+                return Err(LexError::new(LexErrorKind::InvalidNumber, iter.span_from(start)));
+            }
+
+            return u64::from_str_radix(&digits, radix)
+                .map(Token::Number)
+                .map_err(|_| LexError::new(LexErrorKind::NumberTooLarge, iter.span_from(start)));
+        }
+    }
+
+    let mut s: String = iter::once(first)
+        .chain(from_fn(|| iter.next_if(|s| is_decimal_digit(*s) || *s == '_')))
+        .map(to_ascii_digit)
+        .collect();
+    let mut float = false;
+
+    // Only a fraction if a digit comes after the dot, so 1.abc still lexes as a dot
+    if iter.peek_nth(0) == Some('.') && iter.peek_nth(1).is_some_and(is_decimal_digit) {
+        iter.next();
+        s.push('.');
+        s.extend(from_fn(|| iter.next_if(|s| is_decimal_digit(*s) || *s == '_')).map(to_ascii_digit));
+        float = true;
+    }
+
+    let exponent = match (iter.peek_nth(0), iter.peek_nth(1), iter.peek_nth(2)) {
+        (Some('e' | 'E'), Some(digit), _) if is_decimal_digit(digit) => true,
+        (Some('e' | 'E'), Some('+' | '-'), Some(digit)) if is_decimal_digit(digit) => true,
+        _ => false,
+    };
+    if exponent {
+        iter.next();
+        s.push('e');
+        if let Some(sign) = iter.next_if(|s| matches!(s, '+' | '-')) {
+            s.push(sign);
+        }
+        s.extend(from_fn(|| iter.next_if(|s| is_decimal_digit(*s) || *s == '_')).map(to_ascii_digit));
+        float = true;
+    }
+
+    let s: String = s.chars().filter(|s| *s != '_').collect();
+    if float {
+        match s.parse::<f64>() {
+            Ok(num) if num.is_finite() => Ok(Token::Float(num)),
+            Ok(_) => Err(LexError::new(LexErrorKind::NumberTooLarge, iter.span_from(start))),
+            Err(_) => Err(LexError::new(LexErrorKind::InvalidNumber, iter.span_from(start))),
+        }
+    }
+    else {
+        s.parse::<u64>()
+            .map(Token::Number)
+            .map_err(|_| LexError::new(LexErrorKind::NumberTooLarge, iter.span_from(start)))
+    }
 }
 
-fn is_reserved(ch: char) -> bool {
-    matches!(ch, '(' | ')' | '+' | '-' | '*' | '/' | '"' | ' ' | '\n' | '.' | '；' | '。' | '、' | '”' | '’' | '{' | '}')
+fn is_decimal_digit(ch: char) -> bool {
+    matches!(ch, '0'..='9' | '０'..='９')
 }
 
-pub fn dump_tokens(tokens: Vec<Tokens>) {
+/// Turns full width digits into their ascii version, other characters are left the same
+fn to_ascii_digit(ch: char) -> char {
+    match ch {
+        '０'..='９' => char::from_u32(ch as u32 - '０' as u32 + '0' as u32).unwrap_or(ch),
+        _ => ch,
+    }
+}
+
+/// Reads the rest of a string after its opening quote, handling escape sequences.
+/// start is the position of the opening quote, which is pointed to if the string is never closed.
+fn lex_string(iter: &mut Cursor, close: char, start: Span) -> Result<String, LexError> {
+    let quote = iter.span_from(start);
+    let mut s = String::new();
+    // A bad escape is only reported once the end of the string is found, so lexing carries on after the string
+    let mut escape_error: Option<LexError> = None;
+
+    loop {
+        let escape_start = iter.position();
+        match iter.next() {
+            Some(ch) if ch == close => {
+                return match escape_error {
+                    Some(error) => Err(error),
+                    None => Ok(s),
+                };
+            },
+            Some('\\') => match lex_escape(iter, escape_start) {
+                Ok(ch) => s.push(ch),
+                Err(error) => {
+                    escape_error.get_or_insert(error);
+                },
+            },
+            Some(ch) => s.push(ch),
+            None => {
+                return Err(
+                    LexError::new(LexErrorKind::UnterminatedString, quote)
+                        .with_label(iter.position(), Note::ExpectedBeforeEof(close.to_string()).to_string())
+                );
+            }
+        }
+    }
+}
+
+/// Works out the character for an escape sequence, the backslash has already been read
+fn lex_escape(iter: &mut Cursor, start: Span) -> Result<char, LexError> {
+    let ch = match iter.next() {
+        Some(ch) => ch,
+        None => return Err(LexError::new(LexErrorKind::UnterminatedString, iter.span_from(start))),
+    };
+
+    match ch {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        'u' => {
+            if iter.next_if(|s| *s == '{').is_none() {
+                return Err(LexError::new(LexErrorKind::InvalidUnicodeEscape, iter.span_from(start)));
+            }
+
+            let hex: String = from_fn(|| iter.next_if(|s| s.is_ascii_hexdigit())).collect();
+            if iter.next_if(|s| *s == '}').is_none() || hex.is_empty() || hex.len() > 6 {
+                return Err(LexError::new(LexErrorKind::InvalidUnicodeEscape, iter.span_from(start)));
+            }
+
+            u32::from_str_radix(&hex, 16).ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| LexError::new(LexErrorKind::InvalidUnicodeEscape, iter.span_from(start)))
+        },
+        // Quotes and backslashes escape to themselves
+        '\\' | '"' | '\'' | '”' | '’' | '」' | '』' => Ok(ch),
+        _ => Err(LexError::new(LexErrorKind::InvalidEscape(ch), iter.span_from(start))),
+    }
+}
+
+/// Reads a raw string after its opening three quotes, raw strings end with three quotes and don't have escapes
+fn lex_raw_string(iter: &mut Cursor, start: Span) -> Result<String, LexError> {
+    let quote = iter.span_from(start);
+    let mut s = String::new();
+
+    while let Some(ch) = iter.next() {
+        s.push(ch);
+
+        if s.ends_with("\"\"\"") {
+            s.truncate(s.len() - 3);
+            return Ok(s);
+        }
+    }
+
+    Err(
+        LexError::new(LexErrorKind::UnterminatedString, quote)
+            .with_label(iter.position(), Note::ExpectedBeforeEof("\"\"\"".to_string()).to_string())
+    )
+}
+
+/// Identifiers start with a letter or _, which lets identifiers like 変数 work.
+/// This follows XID_Start as closely as the standard library allows.
+fn is_ident_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+/// After the first character identifiers can also have digits and combining marks such as ゛
+fn is_ident_continue(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || matches!(ch,
+        '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' |
+        '\u{20D0}'..='\u{20FF}' | '\u{3099}'..='\u{309A}' | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(token_value) => write!(f, "Number: {}", token_value),
+            Token::Float(token_value) => write!(f, "Float: {}", token_value),
+            Token::String(token_value) => write!(f, "String: {}", token_value),
+            Token::Ident(token_value) => write!(f, "Ident: {}", token_value),
+            Token::DocComment(token_value) => write!(f, "Doc comment: {}", token_value),
+            Token::Bool(token_value) => write!(f, "Bool: {}", token_value),
+            Token::Dash => write!(f, "-"),
+            Token::Eof => write!(f, "End of file"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::Plus => write!(f, "+"),
+            Token::Slash => write!(f, "/"),
+            Token::Star => write!(f, "*"),
+            Token::Dot => write!(f, "."),
+            Token::DotDot => write!(f, ".."),
+            Token::DotDotEqual => write!(f, "..="),
+            Token::Semicolon => write!(f, ";"),
+            Token::Equal => write!(f, "="),
+            Token::DoubleEqual => write!(f, "=="),
+            Token::Not => write!(f, "!"),
+            Token::LeftBracket => write!(f, "{{"),
+            Token::RightBracket => write!(f, "}}"),
+            Token::While => write!(f, "while"),
+            Token::Break => write!(f, "break"),
+            Token::Continue => write!(f, "continue"),
+            Token::For => write!(f, "for"),
+            Token::In => write!(f, "in"),
+            Token::Repeat => write!(f, "repeat"),
+            Token::If => write!(f, "if"),
+            Token::Elseif => write!(f, "else if"),
+            Token::Else => write!(f, "else"),
+            Token::NotEqual => write!(f, "!="),
+            Token::More => write!(f, ">"),
+            Token::Less => write!(f, "<"),
+            Token::EqualMore => write!(f, ">="),
+            Token::EqualLess => write!(f, "<="),
+            Token::Comparator => write!(f, "Comparator, IT IS IMPOSSIBLE FOR THIS TO HAPPEN"),
+            Token::Function => write!(f, "fn"),
+            Token::Return => write!(f, "return"),
+            Token::Comma => write!(f, ","),
+            Token::Print => write!(f, "print"),
+            Token::Println => write!(f, "println"),
+            Token::Eprint => write!(f, "eprint"),
+            Token::Eprintln => write!(f, "eprintln"),
+        }
+    }
+}
+/// Prints every token along with the line and column it starts at
+pub fn dump_tokens(tokens: &[Tokens]) {
     for token in tokens {
-        match token.token {
-            Token::Number(token_value) => println!("Number: {}", token_value),
-            Token::String(token_value) => println!("String: {}", token_value),
-            Token::Other(token_value) => println!("Other: {}", token_value),
-            Token::Bool(token_value) => println!("Bool: {}", token_value),
-            Token::Dash => println!("-"),
-            Token::Eof => println!("End of file"),
-            Token::LeftParen => println!("("),
-            Token::RightParen => println!(")"),
-            Token::Plus => println!("+"),
-            Token::Slash => println!("/"),
-            Token::Star => println!("*"),
-            Token::Dot => println!("."),
-            Token::Semicolon => println!(";"),
-            Token::Equal => println!("="),
-            Token::LeftBracket => println!("{{"),
-            Token::RightBracket => println!("}}"),
-            Token::While => println!("While"),
-            Token::For => println!("For"),
-            Token::Repeat => println!("Repeat"),
-            Token::If => println!("if"),
-            Token::Elseif => println!("else if"),
-            Token::Else => println!("else"),
-            Token::NotEqual => println!("!="),
-            Token::More => println!(">"),
-            Token::Less => println!("<"),
-            Token::EqualMore => println!(">="),
-            Token::EqualLess => println!("<="),
-            Token::Comparator => println!("Comparator, IT IS IMPOSSIBLE FOR THIS TO HAPPEN"),
-            Token::Function => println!("Function"),
-            Token::Comma => println!(","),
-            Token::Print => println!("Print"),
-        }
-    }
-}
\ No newline at end of file
+        dump_token(token);
+    }
+}
+
+/// Prints a single token, used when tokens are dumped as they are lexed
+pub fn dump_token(token: &Tokens) {
+    println!("{}:{}\t{}", token.span.line, token.span.column, token.token);
+}
@@ -0,0 +1,270 @@
+use super::*;
+
+#[test]
+fn test_line_and_column() {
+    let tokens = tokenizer("print(\"abc\")\n  repeat".to_string()).unwrap();
+
+    let positions: Vec<(u64, u64)> = tokens.iter().map(|token| (token.span.line, token.span.column)).collect();
+    assert_eq!(positions, vec![(1, 1), (1, 6), (1, 7), (1, 12), (2, 3), (2, 9)]);
+}
+
+#[test]
+fn test_span_offsets() {
+    let tokens = tokenizer("表示 \"ab\" 12".to_string()).unwrap();
+
+    let offsets: Vec<(usize, usize)> = tokens.iter().map(|token| (token.span.start, token.span.end)).collect();
+    assert_eq!(offsets, vec![(0, 6), (7, 11), (12, 14), (14, 14)]);
+}
+
+fn create_fake_tokens(input: &str) -> Vec<Token> {
+    tokenizer(input.to_string()).unwrap().into_iter().map(|token| token.token).collect()
+}
+
+#[test]
+fn test_comments() {
+    let tokens = tokenizer("# comment\nprint #* block\ncomment *# repeat".to_string()).unwrap();
+
+    let tokens: Vec<(Token, u64)> = tokens.into_iter().map(|token| (token.token, token.span.line)).collect();
+    assert_eq!(tokens, vec![(Token::Print, 2), (Token::Repeat, 3), (Token::Eof, 3)]);
+}
+
+#[test]
+fn test_doc_comment() {
+    assert_eq!(
+        create_fake_tokens("## Adds two numbers\nfn"),
+        vec![Token::DocComment("Adds two numbers".to_string()), Token::Function, Token::Eof]
+    );
+}
+
+#[test]
+fn test_comparison_operators() {
+    assert_eq!(
+        create_fake_tokens("== != < > <= >= =< => ! ="),
+        vec![
+            Token::DoubleEqual, Token::NotEqual, Token::Less, Token::More,
+            Token::EqualLess, Token::EqualMore, Token::EqualLess, Token::EqualMore,
+            Token::Not, Token::Equal, Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_operators_split_identifiers() {
+    assert_eq!(
+        create_fake_tokens("a<=b"),
+        vec![Token::Ident("a".to_string()), Token::EqualLess, Token::Ident("b".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn test_if_else_keywords() {
+    assert_eq!(
+        create_fake_tokens("if else if else"),
+        vec![Token::If, Token::Elseif, Token::Else, Token::Eof]
+    );
+}
+
+#[test]
+fn test_string_escapes() {
+    assert_eq!(
+        create_fake_tokens(r#""a\n\t\\\"\u{3042}""#),
+        vec![Token::String("a\n\t\\\"あ".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn test_raw_string() {
+    assert_eq!(
+        create_fake_tokens("\"\"\"line \"one\"\n\\n two\"\"\" \"\""),
+        vec![Token::String("line \"one\"\n\\n two".to_string()), Token::String(String::new()), Token::Eof]
+    );
+}
+
+#[test]
+fn test_typographic_quotes() {
+    assert_eq!(
+        create_fake_tokens("“a” 「b」 『c』 ‘d’"),
+        vec![
+            Token::String("a".to_string()), Token::String("b".to_string()),
+            Token::String("c".to_string()), Token::String("d".to_string()), Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_unterminated_string() {
+    let error = tokenizer("print(\n  \"abc".to_string()).unwrap_err().remove(0);
+
+    assert_eq!(error.kind, LexErrorKind::UnterminatedString);
+    assert_eq!(error.span, Span { line: 2, column: 3, start: 9, end: 10 });
+}
+
+#[test]
+fn test_invalid_escape() {
+    let error = tokenizer("\"\\q\"".to_string()).unwrap_err().remove(0);
+
+    assert_eq!(error.kind, LexErrorKind::InvalidEscape('q'));
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(
+        create_fake_tokens("0 10 007 1_000 0x1F 0b1010 0o17 １２"),
+        vec![
+            Token::Number(0), Token::Number(10), Token::Number(7), Token::Number(1000),
+            Token::Number(31), Token::Number(10), Token::Number(15), Token::Number(12), Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_floats() {
+    assert_eq!(
+        create_fake_tokens("2.5 1e-3 2E2 1.abc"),
+        vec![
+            Token::Float(2.5), Token::Float(0.001), Token::Float(200.0),
+            Token::Number(1), Token::Dot, Token::Ident("abc".to_string()), Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_number_too_large() {
+    let error = tokenizer("print(99999999999999999999)".to_string()).unwrap_err().remove(0);
+
+    assert_eq!(error.kind, LexErrorKind::NumberTooLarge);
+    assert_eq!(error.span, Span { line: 1, column: 7, start: 6, end: 26 });
+}
+
+#[test]
+fn test_invalid_prefixed_number() {
+    assert_eq!(tokenizer("0b102".to_string()).unwrap_err()[0].kind, LexErrorKind::InvalidNumber);
+    assert_eq!(tokenizer("0x".to_string()).unwrap_err()[0].kind, LexErrorKind::InvalidNumber);
+}
+
+#[test]
+fn test_every_error_reported() {
+    let errors = tokenizer("print(@);\n\"\\q\" 0b2 `".to_string()).unwrap_err();
+
+    let kinds: Vec<LexErrorKind> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![
+        LexErrorKind::InvalidCharacter('@'),
+        LexErrorKind::InvalidEscape('q'),
+        LexErrorKind::InvalidNumber,
+        LexErrorKind::InvalidCharacter('`'),
+    ]);
+}
+
+#[test]
+fn test_identifiers() {
+    assert_eq!(
+        create_fake_tokens("x=1; 変数2、_a,b"),
+        vec![
+            Token::Ident("x".to_string()), Token::Equal, Token::Number(1), Token::Semicolon,
+            Token::Ident("変数2".to_string()), Token::Comma, Token::Ident("_a".to_string()), Token::Comma,
+            Token::Ident("b".to_string()), Token::Eof
+        ]
+    );
+}
+
+#[test]
+fn test_keywords_are_not_identifiers() {
+    assert_eq!(
+        create_fake_tokens("true; repeater"),
+        vec![Token::Bool(true), Token::Semicolon, Token::Ident("repeater".to_string()), Token::Eof]
+    );
+}
+
+#[test]
+fn test_lexer_from_reader() {
+    let source = "print(\"abc\");\nelse if 変数 #* comment *# 12\n";
+    let from_reader: Vec<Tokens> = Lexer::from_reader(source.as_bytes()).map(|token| token.unwrap()).collect();
+
+    assert_eq!(from_reader, tokenizer(source.to_string()).unwrap());
+    assert_eq!(from_reader[5].token, Token::Elseif);
+}
+
+#[test]
+fn test_lexer_keeps_going_after_errors() {
+    let tokens: Vec<Result<Token, LexErrorKind>> = Lexer::new("1 @ else 2")
+        .map(|token| token.map(|token| token.token).map_err(|error| error.kind))
+        .collect();
+
+    assert_eq!(tokens, vec![
+        Ok(Token::Number(1)),
+        Err(LexErrorKind::InvalidCharacter('@')),
+        Ok(Token::Else),
+        Ok(Token::Number(2)),
+        Ok(Token::Eof),
+    ]);
+}
+
+#[test]
+fn test_lexer_reports_invalid_utf8() {
+    let source: &[u8] = b"print(\xff)";
+    let errors: Vec<LexError> = Lexer::from_reader(source).filter_map(|token| token.err()).collect();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0].kind, LexErrorKind::Io(_)));
+}
+
+#[test]
+fn test_japanese_keywords() {
+    let source = "もし 真 それ以外 もし 繰り返す 関数 表示 偽 print";
+    let tokens: Vec<Token> = Lexer::new(source)
+        .with_keywords(Keywords::Japanese)
+        .map(|token| token.unwrap().token)
+        .collect();
+
+    assert_eq!(tokens, vec![
+        Token::If, Token::Bool(true), Token::Elseif, Token::Repeat, Token::Function,
+        Token::Print, Token::Bool(false), Token::Print, Token::Eof
+    ]);
+    assert_eq!(create_fake_tokens("表示"), vec![Token::Ident("表示".to_string()), Token::Eof]);
+}
+
+#[test]
+fn test_japanese_keywords_without_spaces() {
+    let source = "それ以外もし(x)表示(真)関数名";
+    let tokens: Vec<Tokens> = Lexer::new(source)
+        .with_keywords(Keywords::Japanese)
+        .map(|token| token.unwrap())
+        .collect();
+
+    assert_eq!(tokens.iter().map(|token| token.token.clone()).collect::<Vec<Token>>(), vec![
+        Token::Elseif, Token::LeftParen, Token::Ident("x".to_string()), Token::RightParen,
+        Token::Print, Token::LeftParen, Token::Bool(true), Token::RightParen,
+        Token::Function, Token::Ident("名".to_string()), Token::Eof
+    ]);
+    assert_eq!(tokens[0].span, Span { line: 1, column: 1, start: 0, end: 18 });
+}
+
+#[test]
+fn test_keywords_pragma() {
+    assert_eq!(
+        create_fake_tokens("もし\n#pragma keywords japanese\nもし"),
+        vec![Token::Ident("もし".to_string()), Token::If, Token::Eof]
+    );
+
+    let error = tokenizer("#pragma keywords klingon".to_string()).unwrap_err().remove(0);
+    assert_eq!(error.kind, LexErrorKind::InvalidPragma("keywords klingon".to_string()));
+}
+
+#[test]
+fn test_loop_keywords() {
+    assert_eq!(
+        create_fake_tokens("while break continue"),
+        vec![Token::While, Token::Break, Token::Continue, Token::Eof]
+    );
+}
+
+#[test]
+fn test_ranges() {
+    assert_eq!(
+        create_fake_tokens("for i in 0..10 1..=2.5"),
+        vec![
+            Token::For, Token::Ident("i".to_string()), Token::In, Token::Number(0), Token::DotDot, Token::Number(10),
+            Token::Number(1), Token::DotDotEqual, Token::Float(2.5), Token::Eof
+        ]
+    );
+}
@@ -1,49 +1,116 @@
 #![allow(dead_code)]
 
-use lexer::dump_tokens;
+use std::io;
+use std::panic;
+use std::process::ExitCode;
+use std::thread;
 
+use cli::Command;
+use diagnostic::Diagnostic;
+use lexer::Span;
+use messages::{Heading, Note};
+
+mod cli;
+mod diagnostic;
+mod messages;
 mod lexer;
 mod parser;
 mod interpreter;
 mod st;
+mod repl;
 
 #[cfg(test)]
 mod tests;
 
-fn main() {
-    let _short_input = "3+3-2/()1*\"123\"456\"789\"";
-    let _long_input = "3 + 3
-    2 + 2
-    7 - 9
-    \"5235\"
-    123123
-    \"this is a test of the string feature\"
-    42
-    6*7
-    let 
-    test 
-    abc
-    abc.test.abc
-    true
-    \"true\"";
-    let _repeat_test_input = "repeat(3) { abc 7 + 3 * 7 \"test\" }";
-    let _invalid_test = "1+a";
-    let _print_test = "print(\"abc\")";
-
-    // Select which test input you want to use here
-    let input = _print_test.to_string(); 
-    println!("{}", input);
-
-    let tokens = lexer::tokenizer(input.clone());
-    dump_tokens(tokens.clone());
-
-    let instructions = parser::parse(tokens).unwrap();
-
-    println!("Input file: {}", input.clone());
-
-    println!("--- RUNNING ---");
-    
-    let mut variables: Vec<interpreter::Variable> = Vec::new();
-    let mut functions: Vec<interpreter::Function> = Vec::new();
-    interpreter::interpret(instructions, &mut variables, &mut functions).unwrap();
-}
\ No newline at end of file
+/// Scripts are run on their own thread so deep recursion reaches the interpreter's call limit before it runs out of stack.
+/// A debug build needs about 32 MiB for 1000 calls of a function with nothing nested in it, and 8 MiB isn't enough,
+/// so this leaves room for functions that call themselves from inside a few loops and ifs.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn main() -> ExitCode {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter thread")
+        .join()
+        .unwrap_or_else(|error| panic::resume_unwind(error))
+}
+
+fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Mistakes in the arguments are shown in the language from the environment, as --lang may not have been read yet
+    messages::set_lang(messages::lang_from_env());
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            let diagnostic = Diagnostic::new(&Heading::Error.to_string(), message.to_string(), Span::default()).with_code(message.code());
+            diagnostic::report(&diagnostic, "", "");
+            eprintln!("{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if let Some(lang) = command.options().and_then(|options| options.lang) {
+        messages::set_lang(lang);
+    }
+
+    match command {
+        Command::Run { path, options } if path == "-" => {
+            if let (Err(error), source) = cli::run_reader(io::stdin().lock(), &options) {
+                for diagnostic in error.diagnostics() {
+                    diagnostic::report_from(&diagnostic, "<stdin>", &source.text, source.start);
+                }
+                return ExitCode::FAILURE;
+            }
+        },
+        Command::Run { path, options } => {
+            let source = match cli::read_file(&path) {
+                Ok(source) => source,
+                Err(error) => {
+                    report(&error, &path, "");
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            if let Err(error) = cli::run_source(source.clone(), &options) {
+                report(&error, &path, &source);
+                return ExitCode::FAILURE;
+            }
+        },
+        Command::Repl { options } => {
+            if let Err(error) = repl::run(options) {
+                eprintln!("{error}");
+                return ExitCode::FAILURE;
+            }
+        },
+        Command::Check { path, options } => {
+            let source = match cli::read_file(&path) {
+                Ok(source) => source,
+                Err(error) => {
+                    report(&error, &path, "");
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let errors = cli::check_source(source.clone(), &options);
+            for error in &errors {
+                diagnostic::report(error, &path, &source);
+            }
+
+            if !errors.is_empty() {
+                eprintln!("{}", Note::ErrorsFound { count: errors.len(), path });
+                return ExitCode::FAILURE;
+            }
+        },
+        Command::Help => println!("{}", cli::USAGE),
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn report(error: &cli::Error, file_name: &str, source: &str) {
+    for diagnostic in error.diagnostics() {
+        diagnostic::report(&diagnostic, file_name, source);
+    }
+}
@@ -0,0 +1,357 @@
+// Every message shown to the user when something goes wrong, in each language lang-01 supports
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(test)]
+mod tests;
+
+/// Languages that diagnostics can be shown in
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Lang {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl FromStr for Lang {
+    type Err = Message;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "en" | "english" => Ok(Lang::English),
+            "ja" | "japanese" | "日本語" => Ok(Lang::Japanese),
+            _ => Err(Message::UnknownLang(name.to_string())),
+        }
+    }
+}
+
+/// The language messages are currently shown in, set once from --lang or the environment
+static LANG: AtomicU8 = AtomicU8::new(Lang::English as u8);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Japanese,
+        _ => Lang::English,
+    }
+}
+
+/// Works out the language from the locale, e.g. LANG=ja_JP.UTF-8.
+/// LC_ALL and LC_MESSAGES are checked first as they override LANG.
+pub fn lang_from_env() -> Lang {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    lang_from_locale(locale.as_deref().unwrap_or(""))
+}
+
+fn lang_from_locale(locale: &str) -> Lang {
+    if locale.starts_with("ja") {
+        Lang::Japanese
+    }
+    else {
+        Lang::English
+    }
+}
+
+/// The heading a diagnostic is shown under
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Heading {
+    Error,
+    SyntaxError,
+    RuntimeError,
+}
+
+impl Heading {
+    pub fn text(self, lang: Lang) -> &'static str {
+        match (self, lang) {
+            (Heading::Error, Lang::English) => "error",
+            (Heading::Error, Lang::Japanese) => "エラー",
+            (Heading::SyntaxError, Lang::English) => "syntax error",
+            (Heading::SyntaxError, Lang::Japanese) => "構文エラー",
+            (Heading::RuntimeError, Lang::English) => "runtime error",
+            (Heading::RuntimeError, Lang::Japanese) => "実行時エラー",
+        }
+    }
+}
+
+impl fmt::Display for Heading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text(lang()))
+    }
+}
+
+/// Every error that can be reported, each one has a code that stays the same in every language.
+/// E01 codes come from the lexer, E02 from the parser, E03 from the interpreter and E04 from the command line.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Message {
+    // Lexer
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape(char),
+    InvalidUnicodeEscape,
+    InvalidNumber,
+    NumberTooLarge,
+    InvalidCharacter(char),
+    InvalidPragma(String),
+    Io(String),
+
+    // Parser
+    UnexpectedToken(String),
+    Expected {
+        expected: String,
+        found: String,
+    },
+    UnexpectedEof,
+    UnclosedBlock,
+    ExpectedOperator(String),
+    ExpectedExpression(String),
+    LoopControlOutsideLoop(&'static str),
+    ReturnOutsideFunction,
+    NestedFunction,
+    CodeOutsideMain,
+    DuplicateFunction(String),
+    TooDeeplyNested,
+
+    // Interpreter
+    MissingFunction(String),
+    DivideByZero,
+    IntegerOverflow,
+    TypeMismatch {
+        op: &'static str,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    NegateNonNumber(&'static str),
+    NotAnExpression,
+    UndefinedVariable(String),
+    NotIterable(&'static str),
+    RangeNotInt(&'static str),
+    ZeroStep,
+    RepeatCountNotInt(&'static str),
+    NegativeRepeatCount(i64),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    NoReturnValue(String),
+    RecursionLimit(String),
+
+    // Command line
+    ReadFile {
+        path: String,
+        error: String,
+    },
+    UnknownCommand(String),
+    UnknownFlag(String),
+    MissingPath(&'static str),
+    UnexpectedArgument(String),
+    UnknownKeywords(String),
+    UnknownLang(String),
+}
+
+impl Message {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Message::UnterminatedString => "E0101",
+            Message::UnterminatedComment => "E0102",
+            Message::InvalidEscape(_) => "E0103",
+            Message::InvalidUnicodeEscape => "E0104",
+            Message::InvalidNumber => "E0105",
+            Message::NumberTooLarge => "E0106",
+            Message::InvalidCharacter(_) => "E0107",
+            Message::InvalidPragma(_) => "E0108",
+            Message::Io(_) => "E0109",
+
+            Message::UnexpectedToken(_) => "E0201",
+            Message::Expected { .. } => "E0202",
+            Message::UnexpectedEof => "E0203",
+            Message::UnclosedBlock => "E0204",
+            Message::ExpectedOperator(_) => "E0209",
+            Message::ExpectedExpression(_) => "E0210",
+            Message::LoopControlOutsideLoop(_) => "E0211",
+            Message::ReturnOutsideFunction => "E0212",
+            Message::NestedFunction => "E0213",
+            Message::CodeOutsideMain => "E0214",
+            Message::DuplicateFunction(_) => "E0215",
+            Message::TooDeeplyNested => "E0216",
+
+            Message::MissingFunction(_) => "E0302",
+            Message::DivideByZero => "E0304",
+            Message::IntegerOverflow => "E0305",
+            Message::TypeMismatch { .. } => "E0306",
+            Message::NegateNonNumber(_) => "E0307",
+            Message::NotAnExpression => "E0308",
+            Message::UndefinedVariable(_) => "E0309",
+            Message::NotIterable(_) => "E0310",
+            Message::RangeNotInt(_) => "E0311",
+            Message::ZeroStep => "E0312",
+            Message::RepeatCountNotInt(_) => "E0313",
+            Message::NegativeRepeatCount(_) => "E0314",
+            Message::ArityMismatch { .. } => "E0315",
+            Message::NoReturnValue(_) => "E0316",
+            Message::RecursionLimit(_) => "E0317",
+
+            Message::ReadFile { .. } => "E0401",
+            Message::UnknownCommand(_) => "E0402",
+            Message::UnknownFlag(_) => "E0403",
+            Message::MissingPath(_) => "E0404",
+            Message::UnexpectedArgument(_) => "E0405",
+            Message::UnknownKeywords(_) => "E0406",
+            Message::UnknownLang(_) => "E0407",
+        }
+    }
+
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::English => self.english(),
+            Lang::Japanese => self.japanese(),
+        }
+    }
+
+    fn english(&self) -> String {
+        match self {
+            Message::UnterminatedString => "String was never closed".to_string(),
+            Message::UnterminatedComment => "Block comment was never closed, block comments end with *#".to_string(),
+            Message::InvalidEscape(ch) => format!("Unknown escape sequence \\{ch}"),
+            Message::InvalidUnicodeEscape => "Invalid unicode escape, expected \\u{...} with up to 6 hex digits".to_string(),
+            Message::InvalidNumber => "Invalid number literal".to_string(),
+            Message::NumberTooLarge => format!("Number is too large, numbers have to be between {} and {}", i64::MIN, i64::MAX),
+            Message::InvalidCharacter(ch) => format!("Unexpected character {ch:?}"),
+            Message::InvalidPragma(pragma) => format!("Unknown pragma {pragma}, expected #pragma keywords english or #pragma keywords japanese"),
+            Message::Io(error) => format!("Could not read the source code: {error}"),
+
+            Message::UnexpectedToken(token) => format!("{token} token found in unexpected location"),
+            Message::Expected { expected, found } => format!("Expected {expected} found {found} instead"),
+            Message::UnexpectedEof => "Unexpected end of file".to_string(),
+            Message::UnclosedBlock => "Did not close section".to_string(),
+            Message::ExpectedOperator(found) => format!("Expected an operator or ; after the value, found {found} instead"),
+            Message::ExpectedExpression(found) => format!("Expected a value found {found} instead"),
+            Message::LoopControlOutsideLoop(keyword) => format!("{keyword} can only be used inside of a loop"),
+            Message::ReturnOutsideFunction => "return can only be used inside of a function".to_string(),
+            Message::NestedFunction => "Functions can only be defined at the top level, not inside of blocks or other functions".to_string(),
+            Message::CodeOutsideMain => "Only functions can be defined outside of fn main(), this code has to go inside of main".to_string(),
+            Message::DuplicateFunction(name) => format!("Function {name} is defined more than once"),
+            Message::TooDeeplyNested => format!("Too many brackets or blocks inside of each other, they can only go {} deep", crate::parser::MAX_NESTING),
+
+            Message::MissingFunction(name) => format!("Function {name} has not been defined"),
+            Message::DivideByZero => "Cannot divide by zero".to_string(),
+            Message::IntegerOverflow => "Integer overflow".to_string(),
+            Message::TypeMismatch { op, lhs, rhs } => format!("Cannot use {op} on {lhs} and {rhs}"),
+            Message::NegateNonNumber(kind) => format!("Cannot make {kind} negative, only numbers can be made negative"),
+            Message::NotAnExpression => "Node cannot be evaluated as an expression".to_string(),
+            Message::UndefinedVariable(name) => format!("Variable {name} has not been defined"),
+            Message::NotIterable(kind) => format!("Cannot loop over {kind}, for loops go over a range such as 0..10"),
+            Message::RangeNotInt(kind) => format!("Ranges can only be made from Int, found {kind}"),
+            Message::ZeroStep => "The step of a range cannot be 0".to_string(),
+            Message::RepeatCountNotInt(kind) => format!("repeat needs an Int to know how many times to run, found {kind}"),
+            Message::NegativeRepeatCount(count) => format!("repeat cannot run a negative number of times, the count was {count}"),
+            Message::ArityMismatch { name, expected, found } => format!("{name} takes {expected} argument(s) but was given {found}"),
+            Message::NoReturnValue(name) => format!("{name} did not return a value, so it cannot be used as one"),
+            Message::RecursionLimit(name) => format!("Too many calls inside of each other when calling {name}, calls can only go {} deep", crate::interpreter::MAX_CALL_DEPTH),
+
+            Message::ReadFile { path, error } => format!("Could not read {path}: {error}"),
+            Message::UnknownCommand(command) => format!("Unknown command {command}"),
+            Message::UnknownFlag(flag) => format!("Unknown flag {flag}"),
+            Message::MissingPath(command) => format!("{command} expects a file"),
+            Message::UnexpectedArgument(arg) => format!("Unexpected argument {arg}"),
+            Message::UnknownKeywords(name) => format!("Unknown keywords {name}, expected english or japanese"),
+            Message::UnknownLang(name) => format!("Unknown language {name}, expected en or ja"),
+        }
+    }
+
+    fn japanese(&self) -> String {
+        match self {
+            Message::UnterminatedString => "文字列が閉じられていません".to_string(),
+            Message::UnterminatedComment => "ブロックコメントが閉じられていません。ブロックコメントは *# で終わります".to_string(),
+            Message::InvalidEscape(ch) => format!("不明なエスケープシーケンス \\{ch} です"),
+            Message::InvalidUnicodeEscape => "無効なユニコードエスケープです。\\u{...} の中に16進数を6桁まで書いてください".to_string(),
+            Message::InvalidNumber => "無効な数値リテラルです".to_string(),
+            Message::NumberTooLarge => format!("数値が大きすぎます。数値は {} から {} の間でなければなりません", i64::MIN, i64::MAX),
+            Message::InvalidCharacter(ch) => format!("予期しない文字 {ch:?} です"),
+            Message::InvalidPragma(pragma) => format!("不明なプラグマ {pragma} です。#pragma keywords english か #pragma keywords japanese を使ってください"),
+            Message::Io(error) => format!("ソースコードを読み込めませんでした: {error}"),
+
+            Message::UnexpectedToken(token) => format!("予期しない場所に {token} があります"),
+            Message::Expected { expected, found } => format!("{expected} が必要ですが {found} が見つかりました"),
+            Message::UnexpectedEof => "予期しないファイルの終わりです".to_string(),
+            Message::UnclosedBlock => "ブロックが閉じられていません".to_string(),
+            Message::ExpectedOperator(found) => format!("値の後には演算子か ; が必要ですが {found} が見つかりました"),
+            Message::ExpectedExpression(found) => format!("値が必要ですが {found} が見つかりました"),
+            Message::LoopControlOutsideLoop(keyword) => format!("{keyword} はループの中でしか使えません"),
+            Message::ReturnOutsideFunction => "return は関数の中でしか使えません".to_string(),
+            Message::NestedFunction => "関数はトップレベルでしか定義できません。ブロックや他の関数の中では定義できません".to_string(),
+            Message::CodeOutsideMain => "fn main() がある場合、関数の外には関数の定義しか書けません。このコードは main の中に移してください".to_string(),
+            Message::DuplicateFunction(name) => format!("関数 {name} が複数回定義されています"),
+            Message::TooDeeplyNested => format!("括弧やブロックの入れ子が深すぎます。入れ子の深さは {} までです", crate::parser::MAX_NESTING),
+
+            Message::MissingFunction(name) => format!("関数 {name} は定義されていません"),
+            Message::DivideByZero => "0 で割ることはできません".to_string(),
+            Message::IntegerOverflow => "整数がオーバーフローしました".to_string(),
+            Message::TypeMismatch { op, lhs, rhs } => format!("{lhs} と {rhs} に {op} は使えません"),
+            Message::NegateNonNumber(kind) => format!("{kind} は負にできません。負にできるのは数値だけです"),
+            Message::NotAnExpression => "式として評価できません".to_string(),
+            Message::UndefinedVariable(name) => format!("変数 {name} は定義されていません"),
+            Message::NotIterable(kind) => format!("{kind} はループできません。for ループには 0..10 のような範囲を使ってください"),
+            Message::RangeNotInt(kind) => format!("範囲は Int でしか作れませんが {kind} が見つかりました"),
+            Message::ZeroStep => "範囲の step を 0 にすることはできません".to_string(),
+            Message::RepeatCountNotInt(kind) => format!("repeat の回数は Int でなければなりませんが {kind} が見つかりました"),
+            Message::NegativeRepeatCount(count) => format!("repeat の回数を負にすることはできません（回数は {count} でした）"),
+            Message::ArityMismatch { name, expected, found } => format!("{name} の引数は {expected} 個ですが {found} 個渡されました"),
+            Message::NoReturnValue(name) => format!("{name} は値を返さないので値として使えません"),
+            Message::RecursionLimit(name) => format!("{name} を呼び出す時に関数呼び出しが深すぎます。呼び出しの深さは {} までです", crate::interpreter::MAX_CALL_DEPTH),
+
+            Message::ReadFile { path, error } => format!("{path} を読み込めませんでした: {error}"),
+            Message::UnknownCommand(command) => format!("不明なコマンド {command} です"),
+            Message::UnknownFlag(flag) => format!("不明なフラグ {flag} です"),
+            Message::MissingPath(command) => format!("{command} にはファイルを指定してください"),
+            Message::UnexpectedArgument(arg) => format!("予期しない引数 {arg} です"),
+            Message::UnknownKeywords(name) => format!("不明なキーワード {name} です。english か japanese を指定してください"),
+            Message::UnknownLang(name) => format!("不明な言語 {name} です。en か ja を指定してください"),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text(lang()))
+    }
+}
+
+/// Text that isn't an error by itself, such as labels pointing at other parts of the source
+#[derive(Debug, PartialEq, Clone)]
+pub enum Note {
+    BlockOpenedHere,
+    ExpectedBeforeEof(String),
+    ErrorsFound {
+        count: usize,
+        path: String,
+    },
+}
+
+impl Note {
+    pub fn text(&self, lang: Lang) -> String {
+        match (self, lang) {
+            (Note::BlockOpenedHere, Lang::English) => "block opened here".to_string(),
+            (Note::BlockOpenedHere, Lang::Japanese) => "ここでブロックが開かれています".to_string(),
+            (Note::ExpectedBeforeEof(close), Lang::English) => format!("expected {close} before the end of the file"),
+            (Note::ExpectedBeforeEof(close), Lang::Japanese) => format!("ファイルの終わりまでに {close} が必要です"),
+            (Note::ErrorsFound { count, path }, Lang::English) => format!("{count} syntax error(s) found in {path}"),
+            (Note::ErrorsFound { count, path }, Lang::Japanese) => format!("{path} で構文エラーが {count} 個見つかりました"),
+        }
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text(lang()))
+    }
+}
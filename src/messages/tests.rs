@@ -0,0 +1,33 @@
+use super::*;
+
+#[test]
+fn test_lang_from_locale() {
+    assert_eq!(lang_from_locale("ja_JP.UTF-8"), Lang::Japanese);
+    assert_eq!(lang_from_locale("en_GB.UTF-8"), Lang::English);
+    assert_eq!(lang_from_locale(""), Lang::English);
+}
+
+#[test]
+fn test_message_languages() {
+    let message = Message::Expected { expected: "(".to_string(), found: ";".to_string() };
+
+    assert_eq!(message.code(), "E0202");
+    assert_eq!(message.text(Lang::English), "Expected ( found ; instead");
+    assert_eq!(message.text(Lang::Japanese), "( が必要ですが ; が見つかりました");
+    assert_eq!(Heading::SyntaxError.text(Lang::Japanese), "構文エラー");
+}
+
+#[test]
+fn test_parse_lang() {
+    assert_eq!("ja".parse(), Ok(Lang::Japanese));
+    assert_eq!("english".parse(), Ok(Lang::English));
+    assert!("fr".parse::<Lang>().is_err());
+}
+
+#[test]
+fn test_read_file_message() {
+    let message = Message::ReadFile { path: "a.mahou".to_string(), error: "not found".to_string() };
+
+    assert_eq!(message.code(), "E0401");
+    assert_eq!(message.text(Lang::Japanese), "a.mahou を読み込めませんでした: not found");
+}
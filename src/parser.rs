@@ -1,10 +1,11 @@
 // Reimplementation of the parser that isn't terrible implemented
-use crate::lexer::{Tokens, Token}; 
-use crate::interpreter::{Variable};
+use crate::lexer::{LexError, Span, Tokens, Token}; 
+use crate::diagnostic::{Diagnostic, Label};
+use crate::messages::{Heading, Message, Note};
 
-// Tokens is a struct with a Token and a line number
-use std::iter::Peekable;
-use std::slice::Iter;
+// Tokens is a struct with a Token and the span it was lexed from
+use std::fmt;
+use std::iter;
 
 #[cfg(test)]
 mod tests;
@@ -18,6 +19,17 @@ pub enum Operator {
     Divide,
 }
 
+impl Operator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Comparator {
     Equal,      // ==
@@ -28,17 +40,56 @@ pub enum Comparator {
     EqualLess,  // =< or <=
 }
 
+impl Comparator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparator::Equal => "==",
+            Comparator::NotEqual => "!=",
+            Comparator::More => ">",
+            Comparator::Less => "<",
+            Comparator::EqualMore => ">=",
+            Comparator::EqualLess => "<=",
+        }
+    }
+}
+
+
+/// One branch of an if statement, else branches have no condition
+#[derive(Debug, PartialEq, Clone)]
+pub struct Branch {
+    pub condition: Option<Node>,
+    pub nodes: Vec<Node>,
+}
 
 /// Node is the struct used for the instructions which will be eventually be interpreted.
+/// It holds what kind of instruction it is along with where in the source it came from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(kind: NodeKind, span: Span) -> Self {
+        Node { kind, span }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum Node {
+pub enum NodeKind {
     // Values
     Int(i64),
+    Float(f64),
     String(String), 
     Bool(bool),
+    // Reading a variable
+    Variable {
+        name: String,
+    },
     // Set Variables
     SetVariable {
-        var: Variable,
+        name: String,
+        value: Box<Node>,
     },
     // Expressions
     UnaryExpr {
@@ -50,177 +101,562 @@ pub enum Node {
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
-    CombineStr {
-        lhs: Box<Node>,
-        rhs: Box<Node>,
-    },
-    // Comparisions
-    IfBinaryCompare {
+    Compare {
         comparator: Comparator,
         lhs: Box<Node>,
         rhs: Box<Node>,
-        nodes: Vec<Node>,
     },
-    IfUnaryCompare {
-        expected: bool,
-        actual: Box<Node>,
-        nodes: Vec<Node>,
-    },
-    IfElseBinaryCompare {
-        comparator: Comparator,
-        lhs: Box<Node>,
-        rhs: Box<Node>,
-        nodes: Vec<Node>,
+    // if, else if and else, only the first branch with a truthy condition is run
+    If {
+        branches: Vec<Branch>,
     },
-    IfElseUnaryCompare {
-        expected: bool,
-        actual: Box<Node>,
+    // Loops
+    // The count is worked out once before the first iteration, index counts up from 0 if it is given
+    Repeat {
+        count: Box<Node>,
+        index: Option<String>,
         nodes: Vec<Node>
     },
-    Else {
-        nodes: Vec<Node>
+    While {
+        condition: Box<Node>,
+        nodes: Vec<Node>,
     },
-    // Other
-    Repeat {
-        count: i64,
-        nodes: Vec<Node>
+    // for variable in iterable, the variable only exists inside of the loop
+    For {
+        variable: String,
+        iterable: Box<Node>,
+        nodes: Vec<Node>,
     },
+    // start..end or start..=end, only used as what a for loop goes over
+    Range {
+        start: Box<Node>,
+        end: Box<Node>,
+        inclusive: bool,
+        step: Option<Box<Node>>,
+    },
+    // Leave the loop, or skip to its next iteration
+    Break,
+    Continue,
     // Used for running a function
     Function {
         name: String,
-        args: Vec<Variable>,
+        args: Vec<Node>,
     },
-    // Used to for defining a function
+    // Used to for defining a function, params are the names the arguments are given inside of the function
     DefineFunction {
         name: String, 
+        params: Vec<String>,
+        doc: Option<String>, // The ## comments written above the function, kept for tooling
         nodes: Vec<Node>,
-        args: Vec<Variable>
+    },
+    // Leave the function, giving back the value if there is one
+    Return {
+        value: Option<Box<Node>>,
     },
     Eof,
-    // Base functions
+    // Base functions, sep goes between each value and end goes after the last one
     Print {
-        str: String
+        kind: PrintKind,
+        args: Vec<Node>,
+        sep: Option<Box<Node>>,
+        end: Option<Box<Node>>,
     }
 }
 
+/// The different print functions, the ln versions end the line and the e versions write to stderr
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PrintKind {
+    Print,
+    Println,
+    Eprint,
+    Eprintln,
+}
+
+impl PrintKind {
+    pub fn ends_line(self) -> bool {
+        matches!(self, PrintKind::Println | PrintKind::Eprintln)
+    }
+
+    pub fn is_stderr(self) -> bool {
+        matches!(self, PrintKind::Eprint | PrintKind::Eprintln)
+    }
+}
+
+impl NodeKind {
+    /// Expressions produce a value when run, used by the repl to echo results
+    pub fn is_expression(&self) -> bool {
+        matches!(self,
+            NodeKind::Int(_) | NodeKind::Float(_) | NodeKind::String(_) | NodeKind::Bool(_) |
+            NodeKind::Variable { .. } | NodeKind::UnaryExpr { .. } | NodeKind::BinaryExpr { .. } |
+            NodeKind::Compare { .. }
+        )
+    }
+
+    /// Short description of the node without its children, used for debug output
+    pub fn label(&self) -> String {
+        match self {
+            NodeKind::Int(num) => format!("Int {num}"),
+            NodeKind::Float(num) => format!("Float {num}"),
+            NodeKind::String(str) => format!("String {str:?}"),
+            NodeKind::Bool(bool) => format!("Bool {bool}"),
+            NodeKind::SetVariable { name, .. } => format!("SetVariable {name}"),
+            NodeKind::UnaryExpr { op, .. } => format!("UnaryExpr {op:?}"),
+            NodeKind::BinaryExpr { op, .. } => format!("BinaryExpr {op:?}"),
+            NodeKind::Compare { comparator, .. } => format!("Compare {comparator:?}"),
+            NodeKind::If { .. } => "If".to_string(),
+            NodeKind::Repeat { index: Some(index), .. } => format!("Repeat as {index}"),
+            NodeKind::Repeat { index: None, .. } => "Repeat".to_string(),
+            NodeKind::While { .. } => "While".to_string(),
+            NodeKind::For { variable, .. } => format!("For {variable}"),
+            NodeKind::Range { inclusive, step, .. } => {
+                let range = if *inclusive { "..=" } else { ".." };
+                let step = if step.is_some() { " step" } else { "" };
+                format!("Range {range}{step}")
+            },
+            NodeKind::Break => "Break".to_string(),
+            NodeKind::Continue => "Continue".to_string(),
+            NodeKind::Variable { name } => format!("Variable {name}"),
+            NodeKind::Function { name, .. } => format!("Function {name}"),
+            NodeKind::DefineFunction { name, params, .. } => format!("DefineFunction {name}({})", params.join(", ")),
+            NodeKind::Return { .. } => "Return".to_string(),
+            NodeKind::Eof => "Eof".to_string(),
+            NodeKind::Print { kind, sep, end, .. } => {
+                let sep = if sep.is_some() { " sep" } else { "" };
+                let end = if end.is_some() { " end" } else { "" };
+                format!("{kind:?}{sep}{end}")
+            },
+        }
+    }
+
+    /// Nodes held inside of this node, in the order they appear in the source
+    pub fn children(&self) -> Vec<&Node> {
+        match self {
+            NodeKind::UnaryExpr { child, .. } | NodeKind::SetVariable { value: child, .. } => vec![child],
+            NodeKind::Return { value } => value.iter().map(|value| value.as_ref()).collect(),
+            NodeKind::BinaryExpr { lhs, rhs, .. } => vec![lhs, rhs],
+            NodeKind::Function { args, .. } => args.iter().collect(),
+            NodeKind::Print { args, sep, end, .. } => args.iter().chain(sep.as_deref()).chain(end.as_deref()).collect(),
+            NodeKind::Compare { lhs, rhs, .. } => vec![lhs, rhs],
+            NodeKind::If { branches } => branches.iter().flat_map(|branch| branch.condition.iter().chain(&branch.nodes)).collect(),
+            NodeKind::While { condition: first, nodes } | NodeKind::For { iterable: first, nodes, .. } |
+            NodeKind::Repeat { count: first, nodes, .. } => {
+                iter::once(first.as_ref()).chain(nodes).collect()
+            },
+            NodeKind::Range { start, end, step, .. } => [start, end].into_iter().chain(step).map(|node| node.as_ref()).collect(),
+            NodeKind::DefineFunction { nodes, .. } => nodes.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Formats the nodes as a tree, with each child indented under its parent
+pub fn format_ast(nodes: &[Node]) -> String {
+    let mut output = String::new();
+
+    for (i, node) in nodes.iter().enumerate() {
+        format_tree(&mut output, node, "", i == nodes.len() - 1);
+    }
+
+    output
+}
+
+fn format_tree(output: &mut String, node: &Node, prefix: &str, last: bool) {
+    let child_prefix = format_line(output, &node.kind.label(), prefix, last);
+
+    // Each branch of an if is shown as its own line so it is clear which nodes belong to which branch
+    if let NodeKind::If { branches } = &node.kind {
+        for (i, branch) in branches.iter().enumerate() {
+            let label = match (i, &branch.condition) {
+                (0, _) => "if",
+                (_, Some(_)) => "else if",
+                (_, None) => "else",
+            };
+            let branch_prefix = format_line(output, label, &child_prefix, i == branches.len() - 1);
+
+            let children: Vec<&Node> = branch.condition.iter().chain(&branch.nodes).collect();
+            format_children(output, &children, &branch_prefix);
+        }
+        return;
+    }
+
+    format_children(output, &node.kind.children(), &child_prefix);
+}
+
+/// Adds a line to the tree, returning the prefix used by the line's children
+fn format_line(output: &mut String, label: &str, prefix: &str, last: bool) -> String {
+    let branch = if last { "└─ " } else { "├─ " };
+    output.push_str(&format!("{prefix}{branch}{label}\n"));
+
+    format!("{prefix}{}", if last { "   " } else { "│  " })
+}
+
+fn format_children(output: &mut String, children: &[&Node], prefix: &str) {
+    for (i, child) in children.iter().enumerate() {
+        format_tree(output, child, prefix, i == children.len() - 1);
+    }
+}
+
+pub fn dump_ast(nodes: &[Node]) {
+    print!("{}", format_ast(nodes));
+}
+
 #[derive(Debug, PartialEq)]
 pub struct SyntaxError {
-    message: String,
-    line: u64
+    message: Message,
+    span: Span,
+    labels: Vec<Label>
 }
 
 impl SyntaxError {
-    pub fn new(message: String, line: u64) -> Self {
+    pub fn new(message: Message, span: Span) -> Self {
         SyntaxError {
             message,
-            line
+            span,
+            labels: Vec::new()
         }
     }
+
+    /// Points at another part of the source that helps explain the error
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(&Heading::SyntaxError.to_string(), self.message.to_string(), self.span)
+            .with_code(self.message.code())
+            .with_labels(self.labels.clone())
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}:{}: {}", Heading::SyntaxError, self.span.line, self.span.column, self.message)
+    }
+}
+
+/// Tokens waiting to be parsed, they are pulled from the lexer only when the parser needs them.
+/// Lex errors are skipped over and kept, so they can be reported once parsing is done.
+/// Doc comments are taken out too and kept with the token after them, so they can be anywhere in the source.
+pub struct TokenStream<'a> {
+    tokens: Box<dyn Iterator<Item = Result<Tokens, LexError>> + 'a>,
+    peeked: Option<(Tokens, Vec<String>)>,
+    previous: Option<Token>, // The last token handed to the parser, used when recovering from errors
+    depth: usize,            // How many { are open, also used when recovering from errors
+    nesting: usize,          // How many expressions and blocks the parser is inside of
+    docs: Vec<String>,       // Doc comments written right before the previous token
+    errors: Vec<LexError>,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: impl Iterator<Item = Result<Tokens, LexError>> + 'a) -> Self {
+        TokenStream { tokens: Box::new(tokens), peeked: None, previous: None, depth: 0, nesting: 0, docs: Vec::new(), errors: Vec::new() }
+    }
+
+    /// Gets the next token along with the doc comments before it
+    fn pull(&mut self) -> Option<(Tokens, Vec<String>)> {
+        let mut docs: Vec<String> = Vec::new();
+        loop {
+            match self.tokens.next()? {
+                Ok(Tokens { token: Token::DocComment(doc), .. }) => docs.push(doc),
+                Ok(token) => return Some((token, docs)),
+                Err(error) => self.errors.push(error),
+            }
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&Tokens> {
+        if self.peeked.is_none() {
+            self.peeked = self.pull();
+        }
+        self.peeked.as_ref().map(|(token, _)| token)
+    }
+
+    /// Takes the doc comments written right before the token that was just returned by next
+    pub fn take_docs(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.docs)
+    }
+
+    pub fn next_if(&mut self, func: impl FnOnce(&Tokens) -> bool) -> Option<Tokens> {
+        match self.peek() {
+            Some(token) if func(token) => self.next(),
+            _ => None,
+        }
+    }
+
+    /// Lexes whatever the parser didn't get to and returns every lex error that was found
+    pub fn finish(mut self) -> Result<(), Vec<LexError>> {
+        while self.pull().is_some() {}
+
+        if self.errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(self.errors)
+        }
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Tokens;
+
+    fn next(&mut self) -> Option<Tokens> {
+        let (token, docs) = self.peeked.take().or_else(|| self.pull())?;
+        match token.token {
+            Token::LeftBracket => self.depth += 1,
+            Token::RightBracket => self.depth = self.depth.saturating_sub(1),
+            _ => {},
+        }
+        self.previous = Some(token.token.clone());
+        self.docs = docs;
+        Some(token)
+    }
+}
+
+impl From<Vec<Tokens>> for TokenStream<'_> {
+    fn from(tokens: Vec<Tokens>) -> Self {
+        TokenStream::new(tokens.into_iter().map(Ok))
+    }
 }
 
 pub fn parse(tokens: Vec<Tokens>) -> Result<Vec<Node>, SyntaxError> {
-    let mut iter: Peekable<Iter<Tokens>> = tokens.iter().peekable();
+    parse_stream(&mut TokenStream::from(tokens))
+}
 
-    put_into_nodes(&mut iter, Token::Eof)
+/// Parses tokens as they are lexed, lex errors are left in the stream to be checked with finish
+pub fn parse_stream(iter: &mut TokenStream) -> Result<Vec<Node>, SyntaxError> {
+    let nodes = put_into_nodes(iter, Token::Eof, Span::default())?;
+    if let Some(error) = check_nodes(&nodes).into_iter().next() {
+        return Err(error);
+    }
+
+    Ok(nodes)
 }
 
-fn put_into_nodes(iter: &mut Peekable<Iter<Tokens>>, end_token: Token) -> Result<Vec<Node>, SyntaxError> {
+/// Parses the tokens but carries on after an error so every syntax error can be reported at once.
+/// The statements that did parse are still checked, so an error doesn't hide the ones found by the checks.
+pub fn parse_all(tokens: Vec<Tokens>) -> Result<Vec<Node>, Vec<SyntaxError>> {
+    let mut iter = TokenStream::from(tokens);
     let mut nodes: Vec<Node> = Vec::new();
+    let mut errors: Vec<SyntaxError> = Vec::new();
 
-    while let Some(token) = iter.next() {
-        match &token.token {
-            Token::Number(num) => {
-                nodes.append(&mut examine_numbers(iter, num, token.line).unwrap());
+    while let Err(error) = fill_nodes(&mut iter, Token::Eof, Span::default(), &mut nodes) {
+        errors.push(error);
+        synchronize(&mut iter);
+
+        if iter.peek().is_none() {
+            break;
+        }
+    }
+
+    errors.extend(check_nodes(&nodes));
+    if errors.is_empty() {
+        return Ok(nodes);
+    }
+
+    errors.sort_by_key(|error| error.span.start);
+    Err(errors)
+}
+
+/// Runs every check that needs the whole file to be parsed first, returning all of the errors found
+fn check_nodes(nodes: &[Node]) -> Vec<SyntaxError> {
+    let mut errors: Vec<SyntaxError> = Vec::new();
+    check_control_flow(nodes, true, false, false, &mut errors);
+    check_main(nodes, &mut errors);
+    check_duplicate_functions(nodes, &mut errors);
+
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+/// Checks break and continue are only used inside of a loop, return is only used inside of a function
+/// and functions are only defined at the top level, where they can be hoisted.
+fn check_control_flow(nodes: &[Node], top_level: bool, in_loop: bool, in_function: bool, errors: &mut Vec<SyntaxError>) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Break if !in_loop => {
+                errors.push(SyntaxError::new(Message::LoopControlOutsideLoop("break"), node.span));
             },
-            Token::String(str) => {
-                nodes.append(&mut examine_string(iter, str, token.line).unwrap());
+            NodeKind::Continue if !in_loop => {
+                errors.push(SyntaxError::new(Message::LoopControlOutsideLoop("continue"), node.span));
             },
-            Token::Other(name) => {
-                // This is where tokens that don't fall under other token sections go
-                match name.as_str() {
-                    "print" => {
-                        // nodes.append()
-                    },
-
-                    _ => {
-                        todo!()
-                    }
+            NodeKind::Return { .. } if !in_function => {
+                errors.push(SyntaxError::new(Message::ReturnOutsideFunction, node.span));
+            },
+            NodeKind::DefineFunction { .. } if !top_level => {
+                errors.push(SyntaxError::new(Message::NestedFunction, node.span));
+            },
+            NodeKind::Repeat { nodes, .. } | NodeKind::While { nodes, .. } | NodeKind::For { nodes, .. } => {
+                check_control_flow(nodes, false, true, in_function, errors);
+            },
+            NodeKind::DefineFunction { nodes, .. } => check_control_flow(nodes, false, false, true, errors),
+            NodeKind::If { branches } => {
+                for branch in branches {
+                    check_control_flow(&branch.nodes, false, in_loop, in_function, errors);
                 }
             },
-            Token::Function => {
+            _ => {},
+        }
+    }
+}
+
+/// Checks that only functions are defined outside of fn main(), as only main is run when it is defined
+fn check_main(nodes: &[Node], errors: &mut Vec<SyntaxError>) {
+    let has_main = nodes.iter().any(|node| matches!(&node.kind, NodeKind::DefineFunction { name, .. } if name == "main"));
+    if !has_main {
+        return;
+    }
+
+    for node in nodes.iter().filter(|node| !matches!(node.kind, NodeKind::DefineFunction { .. } | NodeKind::Eof)) {
+        errors.push(SyntaxError::new(Message::CodeOutsideMain, node.span));
+    }
+}
+
+/// Checks no function is defined twice, as all of them are hoisted before anything is run
+fn check_duplicate_functions(nodes: &[Node], errors: &mut Vec<SyntaxError>) {
+    let mut defined: Vec<&str> = Vec::new();
 
+    for node in nodes {
+        if let NodeKind::DefineFunction { name, .. } = &node.kind {
+            if defined.contains(&name.as_str()) {
+                errors.push(SyntaxError::new(Message::DuplicateFunction(name.to_string()), node.span));
             }
+            defined.push(name);
+        }
+    }
+}
+
+/// Skips to the end of the statement the error was in so parsing can start again after it.
+/// An error inside a block skips the rest of the block, along with any else branches that come after it.
+fn synchronize(iter: &mut TokenStream) {
+    loop {
+        // The statement has ended once the ; or } that ends it has been read, which may be the token the error was found on
+        if iter.depth == 0 {
+            let block_ended = matches!(iter.previous, Some(Token::RightBracket));
+            if matches!(iter.previous, Some(Token::Semicolon)) {
+                return;
+            }
+            if block_ended && !iter.peek().is_some_and(|next| matches!(next.token, Token::Else | Token::Elseif)) {
+                return;
+            }
+        }
+
+        if iter.next_if(|token| !matches!(token.token, Token::Eof)).is_none() {
+            return;
+        }
+    }
+}
+
+/// Turns tokens into nodes until the end token is reached.
+/// start is where the scope was opened, which is pointed to if the scope is never closed.
+fn put_into_nodes(iter: &mut TokenStream, end_token: Token, start: Span) -> Result<Vec<Node>, SyntaxError> {
+    let mut nodes: Vec<Node> = Vec::new();
+    fill_nodes(iter, end_token, start, &mut nodes)?;
+
+    Ok(nodes)
+}
+
+/// Same as put_into_nodes, but the nodes parsed before an error are kept in nodes
+fn fill_nodes(iter: &mut TokenStream, end_token: Token, start: Span, nodes: &mut Vec<Node>) -> Result<(), SyntaxError> {
+    while let Some(token) = iter.next() {
+        match &token.token {
+            Token::Ident(name) if iter.peek().is_some_and(|next| next.token == Token::Equal) => {
+                nodes.push(set_variable(name.to_string(), token.span, iter)?);
+            },
+            Token::Number(_) | Token::Float(_) | Token::String(_) | Token::Bool(_) |
+            Token::Ident(_) | Token::Dash | Token::LeftParen => {
+                nodes.push(expression_statement(iter, token)?);
+            },
+            Token::Function => {
+                let docs = iter.take_docs();
+                nodes.push(declare_function(iter, token.span, docs)?);
+            },
+            Token::Return => {
+                nodes.push(create_return(iter, token.span)?);
+            },
+            Token::DocComment(_) => {
+                // TokenStream takes doc comments out and gives them to the function declared after them
+            },
             Token::Repeat => {
-                nodes.push(create_repeat(iter, token.line).unwrap());
+                nodes.push(create_repeat(iter, token.span)?);
+            },
+            Token::For => {
+                nodes.push(create_for(iter, token.span)?);
+            },
+            Token::If => {
+                nodes.push(create_if(iter, token.span)?);
             },
-            Token::For => todo!(),
-            Token::If => todo!(),
-            Token::Elseif => todo!(),
-            Token::Else => todo!(),
-            Token::While => todo!(),
-            Token::Bool(bool) => {
-                nodes.push(Node::Bool(*bool));
+            Token::Elseif | Token::Else => {
+                // else is handled by create_if, so here it doesn't follow an if
+                return Err(SyntaxError::new(Message::UnexpectedToken(token.token.to_string()), token.span));
+            },
+            Token::While => {
+                nodes.push(create_while(iter, token.span)?);
+            },
+            Token::Break | Token::Continue => {
+                let kind = if token.token == Token::Break { NodeKind::Break } else { NodeKind::Continue };
+                end_statement(iter, token.span)?;
+                nodes.push(Node::new(kind, token.span));
             },
             Token::RightParen => {
-                
+                return Err(SyntaxError::new(
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
+                ));
             },
             Token::LeftBracket => {
                 // I'm going to start managing next_scope in the repeat tokens now
-                // nodes.push(create_next_scope(iter, &next_scope, token.line).unwrap());
+                // nodes.push(create_next_scope(iter, &next_scope, token.span).unwrap());
 
                 return Err(SyntaxError::new(
-                    "{ token found in unexpected location".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
 
             // Functions
-            Token::Print => {
-                if !matches!(iter.next().unwrap().token, Token::LeftParen) {
-                    return Err(SyntaxError::new(
-                        "Expected ( found other token".to_string(),
-                        token.line
-                    ))
-                }
-
-                let arg_tokens = iter.next().unwrap();
-                match &arg_tokens.token {
-                    Token::String(str) => {
-                        nodes.push(Node::Print { str: str.to_string() });
-                    },
-
-                    _ => {
-                        return Err(SyntaxError::new(
-                            "Expected String found other token instead".to_string(),
-                            token.line
-                        ));
-                    }
-
-                }
-
-                if !matches!(iter.next().unwrap().token, Token::RightParen) {
-                    return Err(SyntaxError::new(
-                        "Expected ) found other token instead".to_string(),
-                        token.line
-                    ));
-                }
-            }
+            Token::Print | Token::Println | Token::Eprint | Token::Eprintln => {
+                let kind = match token.token {
+                    Token::Println => PrintKind::Println,
+                    Token::Eprint => PrintKind::Eprint,
+                    Token::Eprintln => PrintKind::Eprintln,
+                    _ => PrintKind::Print,
+                };
+                nodes.push(create_print(iter, token.span, kind)?);
+            },
             
             // END OF SCOPES -----------------------------------------------------------------------
             Token::RightBracket => {
                 // This occurs when the function is called inside a left bracket
                 if matches!(end_token, Token::RightBracket) {
                     // return to previous scope
-                    return Ok(nodes)
+                    return Ok(())
                 }
+
+                // A } with no block open
+                return Err(SyntaxError::new(
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
+                ));
             },
             Token::Eof => {
                 if matches!(end_token, Token::Eof) {
-                    nodes.push(Node::Eof);
-                    return Ok(nodes);
+                    nodes.push(Node::new(NodeKind::Eof, token.span));
+                    return Ok(());
                 }
                 else {
-                    return Err(SyntaxError::new("Did not close section".to_string(), token.line));
+                    return Err(
+                        SyntaxError::new(Message::UnclosedBlock, token.span)
+                            .with_label(start, Note::BlockOpenedHere.to_string())
+                    );
                 }
             },
             // -------------------------------------------------------------------------------------
@@ -228,387 +664,477 @@ fn put_into_nodes(iter: &mut Peekable<Iter<Tokens>>, end_token: Token) -> Result
             // Error handling ----------------------------------------------------------------------
             Token::Plus => {
                 return Err(SyntaxError::new(
-                    "+ token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                ));
-            },
-            Token::Dash => {
-                return Err(SyntaxError::new(
-                    "- token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
             Token::Star => {
                 return Err(SyntaxError::new(
-                    "* token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
             Token::Slash => {
                 return Err(SyntaxError::new(
-                    "/ token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
-            Token::Dot => {
+            Token::Dot | Token::DotDot | Token::DotDotEqual | Token::In => {
                 return Err(SyntaxError::new(
-                    "Dot token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
             Token::Semicolon => {
-                println!("NON-FATAL ERROR - Two semicolons in a row or semicolon was not properly consumed in parser.rs (Latter is fault of language creator)");
+                // An empty statement, such as a ; after the } of a block, does nothing
             },
             Token::Equal => {
                 return Err(SyntaxError::new(
-                    "Equal token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 ));
             },
-            Token::LeftParen => {
-                return Err(SyntaxError::new(
-                    "} token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                )); 
-            },
             // Comparision tokens
-            Token::NotEqual => {
-                if matches!(end_token, Token::Comparator) {
-                    return Ok(nodes);
-                }
-
-                return Err(SyntaxError::new(
-                    "!= token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                )); 
-            },
-            Token::More => {
-                if matches!(end_token, Token::Comparator) {
-                    return Ok(nodes);
-                }
-
-                return Err(SyntaxError::new(
-                    "> token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                )); 
-            },
-            Token::Less => {
-                if matches!(end_token, Token::Comparator) {
-                    return Ok(nodes);
-                }
-
+            Token::DoubleEqual | Token::Not | Token::NotEqual | Token::More | Token::Less |
+            Token::EqualMore | Token::EqualLess | Token::Comparator => {
                 return Err(SyntaxError::new(
-                    "< token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
                 )); 
             },
-            Token::EqualMore => {
-                if matches!(end_token, Token::Comparator) {
-                    return Ok(nodes);
-                }
-
+            Token::Comma => {
                 return Err(SyntaxError::new(
-                    ">= or => token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                )); 
-            },
-            Token::EqualLess => {
-                if matches!(end_token, Token::Comparator) {
-                    return Ok(nodes);
-                }
-
-                return Err(SyntaxError::new(
-                    "<= or =< token found in unexpected location, this error could also be the result of programming language creator's error".to_string(),
-                    token.line
-                )); 
-            },
-            Token::Comparator => {
-                return Err(SyntaxError::new("Comparator token found, this is impossible".to_string(), token.line));
+                    Message::UnexpectedToken(token.token.to_string()),
+                    token.span
+                ));
             },
-            Token::Comma => todo!(),
             // -------------------------------------------------------------------------------------
         }
     }
     
-    return Err(SyntaxError::new(
-        "End token not found".to_string(),
-        0
+    Err(SyntaxError::new(
+        Message::UnexpectedEof,
+        Span::default()
     ))
 }
 
-fn set_variable(variable_name: String, line: u64, iter: &mut Peekable<Iter<Tokens>>) -> Result<Vec<Node>, SyntaxError> {
-    let mut nodes: Vec<Node> = Vec::new();
-    
-    // Variable support goes here
-    iter.next(); // This is the equal sign
-    let variable_value = iter.next().unwrap();
-    let op_or_end = iter.peek().unwrap(); // Used to check if it is an operation or a semicolon
-    match &variable_value.token {
-        Token::String(value_str) => {
-            match op_or_end.token {
-                Token::Semicolon => {
-                    //nodes.push(Node::SetVariable { name: variable_name.to_string(), value: Box::new(Node::String(value_str.to_string())) });
-                    nodes.push(Node::SetVariable {
-                        var: Variable::String {
-                            name: variable_name.to_string(),
-                            str: value_str.to_string()
-                        }
-                    });
-                    Ok(nodes)
-                },
-                Token::Plus => {
-                    todo!();
-                },
+/// Gets the next token, erroring instead of panicking if there are no tokens left
+fn next_token(iter: &mut TokenStream, span: Span) -> Result<Tokens, SyntaxError> {
+    iter.next().ok_or_else(|| SyntaxError::new(Message::UnexpectedEof, span))
+}
 
-                _ => {
-                    Err(SyntaxError::new("Excepted semicolon or operator after String, got unexpected result instead".to_string(), 
-                        // Using variable_value.line as that is where the error when writing the code would be located
-                        variable_value.line
-                    ))
-                }
-            }
-        },
-        Token::Number(_num) => {
-            todo!();
-        }
+/// Peeks at the next token, erroring instead of panicking if there are no tokens left
+fn peek_token(iter: &mut TokenStream, span: Span) -> Result<Tokens, SyntaxError> {
+    iter.peek().cloned().ok_or_else(|| SyntaxError::new(Message::UnexpectedEof, span))
+}
 
-        _ => {
-            Err(SyntaxError::new("That cannot be stored as a variable".to_string(), line))
-        }
-    } 
+/// Parses name = expr; where the name has already been read, span is the span of the name
+fn set_variable(variable_name: String, span: Span, iter: &mut TokenStream) -> Result<Node, SyntaxError> {
+    expect_token(iter, Token::Equal, span)?;
+    let value = parse_expression(iter, span)?;
+    end_statement(iter, value.span)?;
+
+    let span = span.to(value.span);
+    Ok(Node::new(NodeKind::SetVariable { name: variable_name, value: Box::new(value) }, span))
 }
 
-fn examine_numbers(iter: &mut Peekable<Iter<Tokens>>, num: &i64, line: u64) -> Result<Vec<Node>, SyntaxError> {
-    let mut nodes: Vec<Node> =  Vec::new();
+/// Statements end with a semicolon, this consumes it so it isn't treated as a stray semicolon
+fn skip_semicolon(iter: &mut TokenStream) {
+    iter.next_if(|token| matches!(token.token, Token::Semicolon));
+}
 
-    match iter.peek().unwrap().token {
-        Token::Plus => {
-            iter.next(); // this is equal to the plus
-            let next_value = &iter.next().unwrap().token;
+/// Parses an expression used as a statement, first is the token the expression starts with.
+/// The expression has to be followed by ;, the end of the block or the end of the file.
+fn expression_statement(iter: &mut TokenStream, first: Tokens) -> Result<Node, SyntaxError> {
+    let node = parse_expression_from(iter, first, 0)?;
+    end_statement(iter, node.span)?;
 
-            match next_value {
-                Token::Number(next_num) => {
-                    nodes.push(Node::BinaryExpr { 
-                        op: (Operator::Plus), 
-                        lhs: (Box::new(Node::Int(*num))), 
-                        rhs: (Box::new(Node::Int(*next_num))) 
-                    });
-                },
+    Ok(node)
+}
 
-                _ => {
-                    return Err(SyntaxError::new(
-                        "Cannot combine Number and other type together using '+'".to_string(),
-                        line
-                    ));
-                }
-            }
+/// Checks the statement is followed by ;, the end of the block or the end of the file, consuming the ;
+fn end_statement(iter: &mut TokenStream, span: Span) -> Result<(), SyntaxError> {
+    let next = peek_token(iter, span)?;
+    match next.token {
+        Token::Semicolon => skip_semicolon(iter),
+        Token::RightBracket | Token::Eof => {},
+        other => return Err(SyntaxError::new(Message::ExpectedOperator(other.to_string()), next.span)),
+    }
+
+    Ok(())
+}
+
+/// Unary minus binds tighter than every infix operator, so -2 * 3 is (-2) * 3
+const PREFIX_POWER: u8 = 7;
+
+/// How many expressions and blocks can be inside of each other
+pub const MAX_NESTING: usize = 100;
+
+/// Operators that can go between two operands
+enum Infix {
+    Operator(Operator),
+    Comparator(Comparator),
+}
+
+/// How tightly an infix operator holds onto the operands either side of it.
+/// The right power is higher than the left so operators with the same precedence group from the left.
+fn infix_binding_power(token: &Token) -> Option<(u8, u8, Infix)> {
+    let (left, right, infix) = match token {
+        Token::DoubleEqual => (1, 2, Infix::Comparator(Comparator::Equal)),
+        Token::NotEqual => (1, 2, Infix::Comparator(Comparator::NotEqual)),
+        Token::More => (1, 2, Infix::Comparator(Comparator::More)),
+        Token::Less => (1, 2, Infix::Comparator(Comparator::Less)),
+        Token::EqualMore => (1, 2, Infix::Comparator(Comparator::EqualMore)),
+        Token::EqualLess => (1, 2, Infix::Comparator(Comparator::EqualLess)),
+        Token::Plus => (3, 4, Infix::Operator(Operator::Plus)),
+        Token::Dash => (3, 4, Infix::Operator(Operator::Minus)),
+        Token::Star => (5, 6, Infix::Operator(Operator::Multiply)),
+        Token::Slash => (5, 6, Infix::Operator(Operator::Divide)),
+        _ => return None,
+    };
+
+    Some((left, right, infix))
+}
+
+/// Parses a whole expression, span is used if there are no tokens left
+fn parse_expression(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let first = next_token(iter, span)?;
+    parse_expression_from(iter, first, 0)
+}
+
+/// Pratt parser, keeps adding operators to the expression while they bind tighter than min_power
+fn parse_expression_from(iter: &mut TokenStream, first: Tokens, min_power: u8) -> Result<Node, SyntaxError> {
+    let span = first.span;
+    nested(iter, span, |iter| parse_operators(iter, first, min_power))
+}
+
+fn parse_operators(iter: &mut TokenStream, first: Tokens, min_power: u8) -> Result<Node, SyntaxError> {
+    let mut lhs = parse_operand(iter, first)?;
+
+    while let Some((left_power, right_power, infix)) = iter.peek().and_then(|token| infix_binding_power(&token.token)) {
+        if left_power < min_power {
+            break;
+        }
+
+        let op_token = next_token(iter, lhs.span)?;
+        let rhs_first = next_token(iter, op_token.span)?;
+        let rhs = parse_expression_from(iter, rhs_first, right_power)?;
+
+        let span = lhs.span.to(rhs.span);
+        let (lhs_node, rhs_node) = (Box::new(lhs), Box::new(rhs));
+        lhs = match infix {
+            Infix::Operator(op) => Node::new(NodeKind::BinaryExpr { op, lhs: lhs_node, rhs: rhs_node }, span),
+            Infix::Comparator(comparator) => Node::new(NodeKind::Compare { comparator, lhs: lhs_node, rhs: rhs_node }, span),
+        };
+    }
+
+    Ok(lhs)
+}
+
+/// Parses a single value, variable, function call, negated operand or expression in brackets
+fn parse_operand(iter: &mut TokenStream, token: Tokens) -> Result<Node, SyntaxError> {
+    let kind = match token.token {
+        Token::Number(num) => match i64::try_from(num) {
+            Ok(num) => NodeKind::Int(num),
+            Err(_) => return Err(SyntaxError::new(Message::NumberTooLarge, token.span)),
         },
+        Token::Float(num) => NodeKind::Float(num),
+        Token::String(str) => NodeKind::String(str),
+        Token::Bool(bool) => NodeKind::Bool(bool),
         Token::Dash => {
-            iter.next(); // this is equal to the minus
-            let next_value = &iter.next().unwrap().token;
-
-            match next_value {
-                Token::Number(next_num) => {
-                    nodes.push(Node::BinaryExpr { 
-                        op: (Operator::Minus), 
-                        lhs: (Box::new(Node::Int(*num))), 
-                        rhs: (Box::new(Node::Int(*next_num))) 
-                    });
-                }
-                
-                _ => {
-                    return Err(SyntaxError::new(
-                        "Cannot combine Number and other type together using '-'".to_string(),
-                        line
-                    ));
-                }
+            // i64::MIN is one more than i64::MAX so it only fits once the minus is applied
+            if let Some(literal) = iter.next_if(|next| next.token == Token::Number(i64::MIN.unsigned_abs())) {
+                return Ok(Node::new(NodeKind::Int(i64::MIN), token.span.to(literal.span)));
             }
-        },
-        Token::Star => {
-            iter.next();
-            let next_value = &iter.next().unwrap().token;
-
-            match next_value {
-                Token::Number(next_num) => {
-                    nodes.push(Node::BinaryExpr { 
-                        op: (Operator::Minus), 
-                        lhs: (Box::new(Node::Int(*num))), 
-                        rhs: (Box::new(Node::Int(*next_num))) 
-                    });
-                },
 
-                _ => {
-                    return Err(SyntaxError::new(
-                        "Cannot combine Number and other types together using '*'".to_string(), 
-                        line
-                    ));
-                }
-            }
+            let first = next_token(iter, token.span)?;
+            let child = parse_expression_from(iter, first, PREFIX_POWER)?;
+
+            let span = token.span.to(child.span);
+            return Ok(Node::new(NodeKind::UnaryExpr { op: Operator::Minus, child: Box::new(child) }, span));
         },
-        Token::Slash => {
-            iter.next();
-            let next_value = &iter.next().unwrap().token;
-
-            match next_value {
-                Token::Number(next_num) => {
-                    nodes.push(Node::BinaryExpr { 
-                        op: (Operator::Divide), 
-                        lhs: (Box::new(Node::Int(*num))), 
-                        rhs: (Box::new(Node::Int(*next_num))) 
-                    });
-                },
+        Token::LeftParen => {
+            let inner = parse_expression(iter, token.span)?;
+            let right_paren = expect_token(iter, Token::RightParen, inner.span)?;
 
-                _ => {
-                    return Err(SyntaxError::new(
-                        "Cannot combine Number and other types together using '/'".to_string(), 
-                        line
-                    ));
-                }
+            return Ok(Node::new(inner.kind, token.span.to(right_paren.span)));
+        },
+        Token::Ident(name) => {
+            if iter.next_if(|next| matches!(next.token, Token::LeftParen)).is_none() {
+                NodeKind::Variable { name }
+            }
+            else {
+                let (args, right_paren) = parse_arguments(iter, token.span)?;
+                return Ok(Node::new(NodeKind::Function { name, args }, token.span.to(right_paren)));
             }
         },
+        other => return Err(SyntaxError::new(Message::ExpectedExpression(other.to_string()), token.span)),
+    };
 
-        // Just number
-        Token::Semicolon => {
-            nodes.push(Node::Int(*num));
-        },
+    Ok(Node::new(kind, token.span))
+}
 
-        // Error handling
-        Token::Number(_) => {
-            return Err(SyntaxError::new(
-                "Two number tokens found in a row, make sure there isn't whitespace etc. between numbers".to_string(),
-                line
-            ));
-        },
+/// Parses the arguments of a function call after the (, returning them with the span of the )
+fn parse_arguments(iter: &mut TokenStream, span: Span) -> Result<(Vec<Node>, Span), SyntaxError> {
+    let mut args: Vec<Node> = Vec::new();
+    if let Some(right_paren) = iter.next_if(|next| matches!(next.token, Token::RightParen)) {
+        return Ok((args, right_paren.span));
+    }
 
-        _ => {
-            return Err(SyntaxError::new(
-                "Unexpected token found after number".to_string(),
-                line
-            ));
+    loop {
+        let arg = parse_expression(iter, span)?;
+        let separator = next_token(iter, arg.span)?;
+        args.push(arg);
+
+        match separator.token {
+            Token::Comma => continue,
+            Token::RightParen => return Ok((args, separator.span)),
+            other => {
+                return Err(SyntaxError::new(
+                    Message::Expected { expected: ", or )".to_string(), found: other.to_string() },
+                    separator.span
+                ));
+            }
         }
     }
+}
 
-    Ok(nodes)
+/// Gets the next token and checks that it is the expected token
+fn expect_token(iter: &mut TokenStream, expected: Token, span: Span) -> Result<Tokens, SyntaxError> {
+    let token = next_token(iter, span)?;
+    if token.token != expected {
+        return Err(SyntaxError::new(
+            Message::Expected { expected: expected.to_string(), found: token.token.to_string() },
+            token.span
+        ));
+    }
+
+    Ok(token)
 }
 
-fn examine_string(iter: &mut Peekable<Iter<Tokens>>, str: &String, line: u64) -> Result<Vec<Node>, SyntaxError> {
-    let mut nodes = Vec::new();
+/// Parses repeat(count) { nodes } or repeat(count) as index { nodes }
+fn create_repeat(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let (count, right_paren) = create_condition(iter, span)?;
 
-    match iter.peek().unwrap().token {
-        Token::Equal => {
-            // Variable support goes here
-            set_variable(str.to_string(), line, iter).unwrap();
-        },
-        Token::Semicolon => {
-            nodes.push(Node::String(str.to_string()));
-        },
-        Token::Plus => {
-            iter.next();
-            let next_str = &iter.next().unwrap().token;
-
-            match next_str {
-                Token::String(next_str) => {
-                    nodes.push(Node::CombineStr { 
-                        lhs: (Box::new(Node::String(str.to_string()))), 
-                        rhs: (Box::new(Node::String(next_str.to_string()))) 
-                    });
-                },
+    // as is only a keyword here so it can still be used as a variable name
+    let index = match iter.next_if(|token| matches!(&token.token, Token::Ident(word) if word == "as")) {
+        Some(as_token) => Some(expect_ident(iter, "variable name", as_token.span)?.0),
+        None => None,
+    };
 
-                _ => {
-                    return Err(SyntaxError::new(
-                        "Cannot combine number and other type using '+'".to_string(), 
-                        line
-                    ));
-                }
-            }
-        }
+    let nodes = create_block(iter, right_paren)?;
+    Ok(Node::new(NodeKind::Repeat { count: Box::new(count), index, nodes }, span.to(right_paren)))
+}
 
-        _ => {
-            return Err(SyntaxError::new("Expected semicolon".to_string(), line));
-        }
-    } 
+/// Parses while (condition) { nodes }, the condition is checked before every iteration
+fn create_while(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let (condition, right_paren) = create_condition(iter, span)?;
+    let nodes = create_block(iter, right_paren)?;
 
-    Ok(nodes)
+    Ok(Node::new(NodeKind::While { condition: Box::new(condition), nodes }, span.to(right_paren)))
 }
 
-fn create_repeat(iter: &mut Peekable<Iter<Tokens>>, line: u64) -> Result<Node, SyntaxError> {
-    // have to check the next tokens to see the repeat count
-    // More advanced loops can use a proper scope when going into Paren but I won't for this
-    let left_paren = iter.next().unwrap();
-    let value = iter.next().unwrap();
-    let right_paren = iter.next().unwrap();
+/// Parses for name in iterable { nodes }
+fn create_for(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let (name, name_span) = expect_ident(iter, "variable name", span)?;
+    let in_token = expect_token(iter, Token::In, name_span)?;
+    let iterable = create_iterable(iter, in_token.span)?;
+    let nodes = create_block(iter, iterable.span)?;
 
-    // Error checks
-    if !matches!(left_paren.token, Token::LeftParen) {
-        return Err(SyntaxError::new("Left paren expected after repeat keyword".to_string(), left_paren.line));
-    }
-    else if !matches!(right_paren.token, Token::RightParen) {
-        return Err(SyntaxError::new("Right paren expected after repeat and left paren".to_string(), right_paren.line));
-    }
-    
-    match value.token {
-        Token::Number(num) => {
-            iter.next();
-            let new_nodes = put_into_nodes(iter, Token::RightBracket).unwrap();
-            return Ok(Node::Repeat { count: num, nodes: new_nodes });
-        },
+    let span = span.to(iterable.span);
+    Ok(Node::new(NodeKind::For { variable: name, iterable: Box::new(iterable), nodes }, span))
+}
+
+/// Parses what a for loop goes over, either a range such as 0..10 step 2 or a single expression
+fn create_iterable(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let start = parse_expression(iter, span)?;
+    let Some(range) = iter.next_if(|token| matches!(token.token, Token::DotDot | Token::DotDotEqual)) else {
+        return Ok(start);
+    };
+
+    let end = parse_expression(iter, range.span)?;
+    // step is only a keyword here so it can still be used as a variable name
+    let step = match iter.next_if(|token| matches!(&token.token, Token::Ident(word) if word == "step")) {
+        Some(step_token) => Some(Box::new(parse_expression(iter, step_token.span)?)),
+        None => None,
+    };
+
+    let span = start.span.to(step.as_ref().map_or(end.span, |step| step.span));
+    let inclusive = range.token == Token::DotDotEqual;
+    Ok(Node::new(NodeKind::Range { start: Box::new(start), end: Box::new(end), inclusive, step }, span))
+}
 
-        _ => {
-            Err(SyntaxError::new("Expected number for repeat arg, however got something else".to_string(), line))
+/// Parses an if statement along with any else if and else branches that follow it
+fn create_if(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let (first, condition_span) = create_branch(iter, span)?;
+    let mut branches: Vec<Branch> = vec![first];
+
+    loop {
+        if let Some(else_if) = iter.next_if(|token| matches!(token.token, Token::Elseif)) {
+            branches.push(create_branch(iter, else_if.span)?.0);
+        }
+        else if let Some(else_token) = iter.next_if(|token| matches!(token.token, Token::Else)) {
+            branches.push(Branch { condition: None, nodes: create_block(iter, else_token.span)? });
+            break;
+        }
+        else {
+            break;
         }
     }
+
+    Ok(Node::new(NodeKind::If { branches }, span.to(condition_span)))
 }
 
-fn create_if(iter: &mut Peekable<Iter<Tokens>>, line: u64) -> Result<Node, SyntaxError> {
-    // Makes sure that the 
-    if !matches!(iter.next().unwrap().token, Token::LeftParen) {
-        return Err(SyntaxError::new(
-            "If statement requires ( around comparision".to_string(),
-            line
-        ));
-    }
+/// Parses (condition) { nodes }, returning the branch and the span of the )
+fn create_branch(iter: &mut TokenStream, span: Span) -> Result<(Branch, Span), SyntaxError> {
+    let (condition, right_paren) = create_condition(iter, span)?;
 
-    // I think I have to go in a loop to fully process whatever is on the lhs
-    // let lhs_nodes = put_into_nodes(iter, Token::Comparator).unwrap();
-    // Now need to work out the token that resulted in put_into_nodes ending
-    
-    // If rhs is { then lhs has to be a bool
-    // I've realised that if I was to put a bool in without any comparator this wouldnt work
-    // so I'm goign to work on this later.
-        
-    todo!();
+    let nodes = create_block(iter, right_paren)?;
+    Ok((Branch { condition: Some(condition), nodes }, right_paren))
 }
 
-fn create_else(iter: &mut Peekable<Iter<Tokens>>, line: u64) -> Result<Node, SyntaxError> {
-    if !matches!(iter.next().unwrap().token, Token::LeftBracket) {
-        return Err(SyntaxError::new(
-            "Expected { got different token instead".to_string(),
-            line
-        ));
-    }
+/// Parses (expression), returning the expression and the span of the )
+fn create_condition(iter: &mut TokenStream, span: Span) -> Result<(Node, Span), SyntaxError> {
+    let left_paren = expect_token(iter, Token::LeftParen, span)?;
+    let condition = parse_expression(iter, left_paren.span)?;
+    let right_paren = expect_token(iter, Token::RightParen, condition.span)?;
 
-    let new_nodes = put_into_nodes(iter, Token::RightBracket).unwrap();
-    return Ok(Node::Else { nodes: (new_nodes) });
+    Ok((condition, right_paren.span))
 }
 
-fn declare_function(iter: &mut Peekable<Iter<Tokens>>, line: u64) -> Result<Node, SyntaxError> {
-    if !matches!(iter.next().unwrap().token, Token::LeftParen) {
+/// Parses a { } block, span is the token before the { and is used if there are no tokens left
+fn create_block(iter: &mut TokenStream, span: Span) -> Result<Vec<Node>, SyntaxError> {
+    let left_bracket = next_token(iter, span)?;
+    if !matches!(left_bracket.token, Token::LeftBracket) {
         return Err(SyntaxError::new(
-            "Expected ( got different token instead".to_string(),
-            line
+            Message::Expected { expected: "{".to_string(), found: left_bracket.token.to_string() },
+            left_bracket.span
         ));
     }
 
-    todo!();
+    nested(iter, left_bracket.span, |iter| put_into_nodes(iter, Token::RightBracket, left_bracket.span))
 }
 
-fn create_function_call(iter: &mut Peekable<Iter<Tokens>>, line: u64) -> Result<Node, SyntaxError> {
-    if !matches!(iter.next().unwrap().token, Token::LeftParen) {
-        return Err(SyntaxError::new(
-            "Expected ( got different token instead".to_string(),
-            line
-        ));
+/// Runs parse one level deeper inside of expressions or blocks.
+/// Code nested too deeply is an error, as parsing it would overflow the stack.
+fn nested<T>(iter: &mut TokenStream, span: Span, parse: impl FnOnce(&mut TokenStream) -> Result<T, SyntaxError>) -> Result<T, SyntaxError> {
+    if iter.nesting >= MAX_NESTING {
+        return Err(SyntaxError::new(Message::TooDeeplyNested, span));
     }
 
-    todo!();
-}
\ No newline at end of file
+    iter.nesting += 1;
+    let result = parse(iter);
+    iter.nesting -= 1;
+    result
+}
+
+/// Parses print(values, sep = value, end = value), sep and end can be left out
+fn create_print(iter: &mut TokenStream, span: Span, kind: PrintKind) -> Result<Node, SyntaxError> {
+    let left_paren = expect_token(iter, Token::LeftParen, span)?;
+    let mut args: Vec<Node> = Vec::new();
+    let mut sep: Option<Box<Node>> = None;
+    let mut end: Option<Box<Node>> = None;
+
+    let right_paren = match iter.next_if(|next| matches!(next.token, Token::RightParen)) {
+        Some(right_paren) => right_paren.span,
+        None => loop {
+            let first = next_token(iter, left_paren.span)?;
+            let value_span = match &first.token {
+                // sep and end are only options when followed by =, otherwise they are variables to print
+                Token::Ident(name) if (name == "sep" || name == "end") && iter.peek().is_some_and(|next| next.token == Token::Equal) => {
+                    let is_sep = name == "sep";
+                    let equal = next_token(iter, first.span)?;
+                    let value = Box::new(parse_expression(iter, equal.span)?);
+                    let value_span = value.span;
+
+                    if is_sep {
+                        sep = Some(value);
+                    }
+                    else {
+                        end = Some(value);
+                    }
+                    value_span
+                },
+                _ => {
+                    let arg = parse_expression_from(iter, first, 0)?;
+                    let arg_span = arg.span;
+                    args.push(arg);
+                    arg_span
+                },
+            };
+
+            let separator = next_token(iter, value_span)?;
+            match separator.token {
+                Token::Comma => continue,
+                Token::RightParen => break separator.span,
+                other => {
+                    return Err(SyntaxError::new(
+                        Message::Expected { expected: ", or )".to_string(), found: other.to_string() },
+                        separator.span
+                    ));
+                }
+            }
+        },
+    };
+
+    let span = span.to(right_paren);
+    end_statement(iter, span)?;
+    Ok(Node::new(NodeKind::Print { kind, args, sep, end }, span))
+}
+
+/// Parses fn name(params) { nodes }, docs are the lines of the doc comment above it
+fn declare_function(iter: &mut TokenStream, span: Span, docs: Vec<String>) -> Result<Node, SyntaxError> {
+    let (name, name_span) = expect_ident(iter, "function name", span)?;
+    let left_paren = expect_token(iter, Token::LeftParen, name_span)?;
+
+    let mut params: Vec<String> = Vec::new();
+    let right_paren = match iter.next_if(|next| matches!(next.token, Token::RightParen)) {
+        Some(right_paren) => right_paren.span,
+        None => loop {
+            let (param, param_span) = expect_ident(iter, "parameter name", left_paren.span)?;
+            params.push(param);
+
+            let separator = next_token(iter, param_span)?;
+            match separator.token {
+                Token::Comma => continue,
+                Token::RightParen => break separator.span,
+                other => {
+                    return Err(SyntaxError::new(
+                        Message::Expected { expected: ", or )".to_string(), found: other.to_string() },
+                        separator.span
+                    ));
+                }
+            }
+        },
+    };
+
+    let nodes = create_block(iter, right_paren)?;
+    let doc = if docs.is_empty() { None } else { Some(docs.join("\n")) };
+    Ok(Node::new(NodeKind::DefineFunction { name, params, doc, nodes }, span.to(right_paren)))
+}
+
+/// Parses return; or return value;
+fn create_return(iter: &mut TokenStream, span: Span) -> Result<Node, SyntaxError> {
+    let next = peek_token(iter, span)?;
+    let value = match next.token {
+        Token::Semicolon | Token::RightBracket | Token::Eof => None,
+        _ => Some(Box::new(parse_expression(iter, span)?)),
+    };
+
+    let span = value.as_ref().map_or(span, |value| span.to(value.span));
+    end_statement(iter, span)?;
+    Ok(Node::new(NodeKind::Return { value }, span))
+}
+
+/// Gets the next token and checks it is a name, expected describes what the name is for in the error
+fn expect_ident(iter: &mut TokenStream, expected: &str, span: Span) -> Result<(String, Span), SyntaxError> {
+    let token = next_token(iter, span)?;
+    match token.token {
+        Token::Ident(name) => Ok((name, token.span)),
+        other => Err(SyntaxError::new(
+            Message::Expected { expected: expected.to_string(), found: other.to_string() },
+            token.span
+        )),
+    }
+}
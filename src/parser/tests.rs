@@ -8,19 +8,15 @@ use super::*;
 
 // Got told to use 'static lifetime, have to recheck if this is correct later
 fn create_fake_tokens(input: String) -> Vec<Tokens> {
-    let input = input;
-
-    let tokens = lexer::tokenizer(input);
-    
-    return tokens;
+    lexer::tokenizer(input).unwrap()
 }
 
 fn create_fake_node(node: Node) -> Result<Vec<Node>, SyntaxError> {
-    let mut node_vec: Vec<Node> = Vec::new();
-
-    node_vec.push(node);
+    Ok(vec![node])
+}
 
-    return Ok(node_vec);
+fn create_fake_span(line: u64, column: u64, start: usize, end: usize) -> Span {
+    Span { line, column, start, end }
 }
 
 #[test]
@@ -28,40 +24,392 @@ fn test_set_variable() {
     let binding = create_fake_tokens(
         "= \"the answer to life the universe and everything\";".to_string()
     );
-    let mut iter = binding.iter().peekable();
+    let mut iter = TokenStream::from(binding);
     assert_eq!(
-        set_variable("variable_name".to_string(), 42, &mut iter), 
-        create_fake_node(Node::SetVariable 
-            { var: Variable::String { 
+        set_variable("variable_name".to_string(), Span::default(), &mut iter).map(|node| vec![node]), 
+        create_fake_node(Node::new(
+            NodeKind::SetVariable { 
                 name: "variable_name".to_string(), 
-                str: "the answer to life the universe and everything".to_string() 
-            }}
-        )
+                value: Box::new(Node::new(
+                    NodeKind::String("the answer to life the universe and everything".to_string()),
+                    create_fake_span(1, 3, 2, 50)
+                ))
+            },
+            create_fake_span(0, 0, 0, 50)
+        ))
     );
 }
 
 #[test]
-fn test_examine_numbers_binaryexpr_plus() {
+fn test_parse_assignment() {
+    let nodes = parse(create_fake_tokens("x = 1 + y;
+x;".to_string())).unwrap();
+
+    assert_eq!(
+        format_ast(&nodes),
+        "├─ SetVariable x\n│  └─ BinaryExpr Plus\n│     ├─ Int 1\n│     └─ Variable y\n├─ Variable x\n└─ Eof\n"
+    );
+}
+
+#[test]
+fn test_parse_expression_binaryexpr_plus() {
     let binding = create_fake_tokens(
-        "+9".to_string()
+        "1+9".to_string()
     );
-    let mut iter = binding.iter().peekable();
+    let mut iter = TokenStream::from(binding);
     assert_eq!(
-        examine_numbers(&mut iter, &1, 42),
-        create_fake_node(
-            Node::BinaryExpr { 
+        parse_expression(&mut iter, Span::default()).map(|node| vec![node]),
+        create_fake_node(Node::new(
+            NodeKind::BinaryExpr { 
                 op: (Operator::Plus), 
-                lhs: Box::new(Node::Int(1)), 
-                rhs: Box::new(Node::Int(9)) 
-            }
-        )
+                lhs: Box::new(Node::new(NodeKind::Int(1), create_fake_span(1, 1, 0, 1))), 
+                rhs: Box::new(Node::new(NodeKind::Int(9), create_fake_span(1, 3, 2, 3))) 
+            },
+            create_fake_span(1, 1, 0, 3)
+        ))
+    );
+}
+
+#[test]
+fn test_parse_expression_string() {
+    let binding = create_fake_tokens(
+        "\"a\" + \"b\"".to_string()
+    );
+    let mut iter = TokenStream::from(binding);
+    let node = parse_expression(&mut iter, Span::default()).unwrap();
+
+    assert_eq!(format_ast(&[node]), "└─ BinaryExpr Plus\n   ├─ String \"a\"\n   └─ String \"b\"\n");
+}
+
+#[test]
+fn test_parse_expression_precedence() {
+    let binding = create_fake_tokens(
+        "(1 + 2) * x - f(3) / 4 - -5".to_string()
     );
+    let mut iter = TokenStream::from(binding);
+    let node = parse_expression(&mut iter, Span::default()).unwrap();
+
+    assert_eq!(node.span, create_fake_span(1, 1, 0, 27));
+    let expected = [
+        "└─ BinaryExpr Minus\n",
+        "   ├─ BinaryExpr Minus\n",
+        "   │  ├─ BinaryExpr Multiply\n",
+        "   │  │  ├─ BinaryExpr Plus\n",
+        "   │  │  │  ├─ Int 1\n",
+        "   │  │  │  └─ Int 2\n",
+        "   │  │  └─ Variable x\n",
+        "   │  └─ BinaryExpr Divide\n",
+        "   │     ├─ Function f\n",
+        "   │     │  └─ Int 3\n",
+        "   │     └─ Int 4\n",
+        "   └─ UnaryExpr Minus\n",
+        "      └─ Int 5\n",
+    ];
+    assert_eq!(format_ast(&[node]), expected.concat());
+}
+
+#[test]
+fn test_parse_expression_errors() {
+    let error = parse(create_fake_tokens("1 + ;".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::ExpectedExpression(";".to_string()));
+
+    let error = parse(create_fake_tokens("(1 + 2;".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::Expected { expected: ")".to_string(), found: ";".to_string() });
+
+    let error = parse(create_fake_tokens("3 4;".to_string())).unwrap_err();
+    assert_eq!(error.span(), create_fake_span(1, 3, 2, 3));
+}
+
+#[test]
+fn test_syntax_error_display() {
+    let error = parse(create_fake_tokens("3 4;".to_string())).unwrap_err();
+    assert_eq!(error.to_string(), format!("{} 1:3: {}", Heading::SyntaxError, error.message()));
+}
+
+#[test]
+fn test_parse_smallest_int() {
+    let mut iter = TokenStream::from(create_fake_tokens("-9223372036854775808 - 1".to_string()));
+    let node = parse_expression(&mut iter, Span::default()).unwrap();
+    assert_eq!(format_ast(&[node]), "└─ BinaryExpr Minus\n   ├─ Int -9223372036854775808\n   └─ Int 1\n");
+
+    let error = parse(create_fake_tokens("x = 9223372036854775808;".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::NumberTooLarge);
+    assert_eq!(error.span(), create_fake_span(1, 5, 4, 23));
+
+    let error = parse(create_fake_tokens("x = -(9223372036854775808);".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::NumberTooLarge);
+}
+
+#[test]
+fn test_format_ast() {
+    let abc = Node::new(NodeKind::String("abc".to_string()), Span::default());
+    let print = Node::new(NodeKind::Print { kind: PrintKind::Println, args: vec![abc], sep: None, end: None }, Span::default());
+    let count = Box::new(Node::new(NodeKind::Int(2), Span::default()));
+    let nodes = vec![
+        Node::new(NodeKind::Repeat { count, index: None, nodes: vec![print] }, Span::default()),
+        Node::new(NodeKind::Eof, Span::default()),
+    ];
+
+    assert_eq!(
+        format_ast(&nodes),
+        "├─ Repeat\n│  ├─ Int 2\n│  └─ Println\n│     └─ String \"abc\"\n└─ Eof\n"
+    );
+}
+
+#[test]
+fn test_node_spans() {
+    let nodes = parse(create_fake_tokens("print(\"a\");\n  3 + 4;".to_string())).unwrap();
+
+    let spans: Vec<Span> = nodes.iter().map(|node| node.span).collect();
+    assert_eq!(spans, vec![
+        create_fake_span(1, 1, 0, 10),
+        create_fake_span(2, 3, 14, 19),
+        create_fake_span(2, 9, 20, 20),
+    ]);
+}
+
+#[test]
+fn test_parse_stream_keeps_lex_errors() {
+    let mut tokens = TokenStream::new(lexer::Lexer::new("print(\"a\");\n@ 3;"));
+    let nodes = parse_stream(&mut tokens).unwrap();
+
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(tokens.finish().unwrap_err()[0].span, create_fake_span(2, 1, 12, 13));
+}
+
+#[test]
+fn test_parse_if_else() {
+    let nodes = parse(create_fake_tokens("if (x == 1) { 1; } else if (x > 2 + 3) { 2; } else { 3; }".to_string())).unwrap();
+
+    let expected = [
+        "├─ If\n",
+        "│  ├─ if\n",
+        "│  │  ├─ Compare Equal\n",
+        "│  │  │  ├─ Variable x\n",
+        "│  │  │  └─ Int 1\n",
+        "│  │  └─ Int 1\n",
+        "│  ├─ else if\n",
+        "│  │  ├─ Compare More\n",
+        "│  │  │  ├─ Variable x\n",
+        "│  │  │  └─ BinaryExpr Plus\n",
+        "│  │  │     ├─ Int 2\n",
+        "│  │  │     └─ Int 3\n",
+        "│  │  └─ Int 2\n",
+        "│  └─ else\n",
+        "│     └─ Int 3\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+    assert_eq!(nodes[0].span, create_fake_span(1, 1, 0, 11));
+}
+
+#[test]
+fn test_else_without_if_is_error() {
+    let error = parse(create_fake_tokens("1;\nelse { 2; }".to_string())).unwrap_err();
+
+    assert_eq!(*error.message(), Message::UnexpectedToken("else".to_string()));
+    assert_eq!(error.span(), create_fake_span(2, 1, 3, 7));
+}
+
+#[test]
+fn test_parse_while() {
+    let nodes = parse(create_fake_tokens("while (x < 3) { x = x + 1; continue; }".to_string())).unwrap();
+
+    let expected = [
+        "├─ While\n",
+        "│  ├─ Compare Less\n",
+        "│  │  ├─ Variable x\n",
+        "│  │  └─ Int 3\n",
+        "│  ├─ SetVariable x\n",
+        "│  │  └─ BinaryExpr Plus\n",
+        "│  │     ├─ Variable x\n",
+        "│  │     └─ Int 1\n",
+        "│  └─ Continue\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+    assert_eq!(nodes[0].span, create_fake_span(1, 1, 0, 13));
+}
+
+#[test]
+fn test_break_outside_loop_is_error() {
+    let error = parse(create_fake_tokens("if (true) {\n  break;\n}".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::LoopControlOutsideLoop("break"));
+    assert_eq!(error.span(), create_fake_span(2, 3, 14, 19));
+
+    let errors = parse_all(create_fake_tokens("continue;\nrepeat(2) { if (true) { break; } }".to_string())).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(*errors[0].message(), Message::LoopControlOutsideLoop("continue"));
+}
+
+#[test]
+fn test_parse_for() {
+    let nodes = parse(create_fake_tokens("for i in 0..=n step 2 { i; }\nfor c in word { }".to_string())).unwrap();
+
+    let expected = [
+        "├─ For i\n",
+        "│  ├─ Range ..= step\n",
+        "│  │  ├─ Int 0\n",
+        "│  │  ├─ Variable n\n",
+        "│  │  └─ Int 2\n",
+        "│  └─ Variable i\n",
+        "├─ For c\n",
+        "│  └─ Variable word\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+    assert_eq!(nodes[0].span, create_fake_span(1, 1, 0, 21));
+
+    let error = parse(create_fake_tokens("for 1 in 0..2 { }".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::Expected { expected: "variable name".to_string(), found: "Number: 1".to_string() });
+}
+
+#[test]
+fn test_parse_repeat_expression() {
+    let nodes = parse(create_fake_tokens("repeat(n * 2) as i { i; }".to_string())).unwrap();
+
+    let expected = [
+        "├─ Repeat as i\n",
+        "│  ├─ BinaryExpr Multiply\n",
+        "│  │  ├─ Variable n\n",
+        "│  │  └─ Int 2\n",
+        "│  └─ Variable i\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+    assert_eq!(nodes[0].span, create_fake_span(1, 1, 0, 13));
+}
+
+#[test]
+fn test_parse_function() {
+    let nodes = parse(create_fake_tokens("fn add(a, b) {\n  return a + b;\n}\nadd(1, 2);".to_string())).unwrap();
+
+    let expected = [
+        "├─ DefineFunction add(a, b)\n",
+        "│  └─ Return\n",
+        "│     └─ BinaryExpr Plus\n",
+        "│        ├─ Variable a\n",
+        "│        └─ Variable b\n",
+        "├─ Function add\n",
+        "│  ├─ Int 1\n",
+        "│  └─ Int 2\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+    assert_eq!(nodes[0].span, create_fake_span(1, 1, 0, 12));
+}
+
+#[test]
+fn test_function_errors() {
+    let error = parse(create_fake_tokens("fn add(a, 1) { }".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::Expected { expected: "parameter name".to_string(), found: "Number: 1".to_string() });
+
+    let error = parse(create_fake_tokens("repeat(2) { return 1; }".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::ReturnOutsideFunction);
+    assert_eq!(error.span(), create_fake_span(1, 13, 12, 20));
+
+    let error = parse(create_fake_tokens("fn f() { break; }".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::LoopControlOutsideLoop("break"));
 }
 
 #[test]
-fn test_examine_string() {
-    // let binding = create_fake_tokens(
-    //     ""
-    // )
-    // examine_string();
+fn test_nested_function_is_error() {
+    let error = parse(create_fake_tokens("fn f() {\n  fn g() { }\n}".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::NestedFunction);
+    assert_eq!(error.span(), create_fake_span(2, 3, 11, 17));
+
+    let error = parse(create_fake_tokens("if (true) { fn g() { } }".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::NestedFunction);
+}
+
+#[test]
+fn test_duplicate_function_is_error() {
+    let error = parse(create_fake_tokens("fn f() { }\nfn g() { }\nfn f() { }".to_string())).unwrap_err();
+
+    assert_eq!(*error.message(), Message::DuplicateFunction("f".to_string()));
+    assert_eq!(error.span(), create_fake_span(3, 1, 22, 28));
+}
+
+#[test]
+fn test_code_outside_main_is_error() {
+    assert!(parse(create_fake_tokens("fn main() { f(); }\nfn f() { }".to_string())).is_ok());
+
+    let error = parse(create_fake_tokens("fn main() { }\nx = 1;".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::CodeOutsideMain);
+    assert_eq!(error.span(), create_fake_span(2, 1, 14, 19));
+}
+
+#[test]
+fn test_parse_print() {
+    let nodes = parse(create_fake_tokens("print(1, x + 2, f(), end = \"!\", sep = \", \");\neprintln();".to_string())).unwrap();
+
+    let expected = [
+        "├─ Print sep end\n",
+        "│  ├─ Int 1\n",
+        "│  ├─ BinaryExpr Plus\n",
+        "│  │  ├─ Variable x\n",
+        "│  │  └─ Int 2\n",
+        "│  ├─ Function f\n",
+        "│  ├─ String \", \"\n",
+        "│  └─ String \"!\"\n",
+        "├─ Eprintln\n",
+        "└─ Eof\n",
+    ];
+    assert_eq!(format_ast(&nodes), expected.concat());
+
+    let error = parse(create_fake_tokens("println(1 2);".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::Expected { expected: ", or )".to_string(), found: "Number: 2".to_string() });
+}
+
+#[test]
+fn test_stray_comma_is_error() {
+    let errors = parse_all(create_fake_tokens("x = 1;\n, y = 2;".to_string())).unwrap_err();
+
+    assert_eq!(*errors[0].message(), Message::UnexpectedToken(",".to_string()));
+    assert_eq!(errors[0].span(), create_fake_span(2, 1, 7, 8));
+}
+
+#[test]
+fn test_stray_closing_brackets_are_errors() {
+    let error = parse(create_fake_tokens("x = 1;\n)".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::UnexpectedToken(")".to_string()));
+    assert_eq!(error.span(), create_fake_span(2, 1, 7, 8));
+
+    let error = parse(create_fake_tokens("x = 1;\n}".to_string())).unwrap_err();
+    assert_eq!(*error.message(), Message::UnexpectedToken("}".to_string()));
+}
+
+#[test]
+fn test_empty_statements_are_skipped() {
+    let nodes = parse(create_fake_tokens("repeat(2) { 1;; };\n;".to_string())).unwrap();
+
+    assert_eq!(format_ast(&nodes), "├─ Repeat\n│  ├─ Int 2\n│  └─ Int 1\n└─ Eof\n");
+}
+
+#[test]
+fn test_doc_comments() {
+    let source = "## Adds two numbers\n## together\nfn add(a, b) { return a + b; }\n## not attached\nx = add(1, ## inside a call\n 2);\nif (x) ## before a block\n{ }\n## before else\nelse { }\nfn sub(a, b) { return a - b; }";
+    let nodes = parse(create_fake_tokens(source.to_string())).unwrap();
+
+    let docs: Vec<Option<String>> = nodes.iter().filter_map(|node| match &node.kind {
+        NodeKind::DefineFunction { doc, .. } => Some(doc.clone()),
+        _ => None,
+    }).collect();
+    assert_eq!(docs, vec![Some("Adds two numbers\ntogether".to_string()), None]);
+}
+
+#[test]
+fn test_too_deeply_nested() {
+    let source = format!("x = {}1{};", "(".repeat(10_000), ")".repeat(10_000));
+    let error = parse(create_fake_tokens(source)).unwrap_err();
+    assert_eq!(*error.message(), Message::TooDeeplyNested);
+
+    let source = format!("x = {}1;", "-".repeat(10_000));
+    assert_eq!(*parse(create_fake_tokens(source)).unwrap_err().message(), Message::TooDeeplyNested);
+
+    let source = format!("{}{}", "if (true) { ".repeat(10_000), "}".repeat(10_000));
+    assert_eq!(*parse(create_fake_tokens(source)).unwrap_err().message(), Message::TooDeeplyNested);
+
+    let source = format!("x = {}1{};", "(".repeat(MAX_NESTING - 1), ")".repeat(MAX_NESTING - 1));
+    assert!(parse(create_fake_tokens(source)).is_ok());
 }
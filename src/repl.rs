@@ -0,0 +1,174 @@
+use crate::cli::{self, Error, Options};
+use crate::diagnostic;
+use crate::lexer::{Lexer, Token};
+use crate::parser::NodeKind;
+use crate::interpreter::{self, Function, Value, Variable};
+
+use std::io::{self, BufRead, Write};
+
+#[cfg(test)]
+mod tests;
+
+const PROMPT: &str = "> ";
+const CONTINUE_PROMPT: &str = "... ";
+
+/// Keeps variables and functions alive between each entry
+#[derive(Default)]
+pub struct Repl {
+    variables: Vec<Variable>,
+    functions: Vec<Function>,
+    buffer: String,
+    last_entry: String, // Kept so errors can show the source they came from
+    options: Options,
+}
+
+impl Repl {
+    pub fn with_options(options: Options) -> Self {
+        interpreter::set_trace(options.trace);
+
+        Repl { options, ..Self::default() }
+    }
+
+    /// Adds a line to the current entry.
+    /// Returns None when the entry is incomplete and more lines are needed,
+    /// otherwise runs the entry and returns the values of any expression statements.
+    pub fn feed(&mut self, line: &str) -> Option<Result<Vec<Value>, Error>> {
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+
+        // An if waits for the next line in case it starts with else, a blank line runs it straight away
+        if is_incomplete(&self.buffer) || (awaits_else(&self.buffer) && !line.trim().is_empty()) {
+            return None;
+        }
+
+        Some(self.run_buffer())
+    }
+
+    /// Runs whatever is left of the current entry, used once there are no more lines to feed
+    pub fn finish(&mut self) -> Option<Result<Vec<Value>, Error>> {
+        if !self.is_continuing() {
+            return None;
+        }
+
+        Some(self.run_buffer())
+    }
+
+    fn run_buffer(&mut self) -> Result<Vec<Value>, Error> {
+        let input = std::mem::take(&mut self.buffer);
+        self.last_entry = input.clone();
+        self.execute(input)
+    }
+
+    /// True if lines have been fed that haven't been run yet
+    pub fn is_continuing(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    fn execute(&mut self, input: String) -> Result<Vec<Value>, Error> {
+        let instructions = cli::parse_source(&input, &self.options)?;
+        interpreter::hoist_functions(&instructions, &mut self.functions);
+
+        let mut values: Vec<Value> = Vec::new();
+        for instruction in instructions {
+            if matches!(instruction.kind, NodeKind::Eof) {
+                break;
+            }
+
+            if let NodeKind::Function { name, args } = &instruction.kind {
+                let value = interpreter::call_function(name, args, instruction.span, &self.variables, &mut self.functions)?;
+                values.extend(value);
+            }
+            else if instruction.kind.is_expression() {
+                values.push(interpreter::evaluate(&instruction, &self.variables, &mut self.functions)?);
+            }
+            else {
+                interpreter::interpret(vec![instruction], &mut self.variables, &mut self.functions)?;
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+/// Checks for unclosed brackets, strings and block comments, which mean the entry carries on to the next line
+fn is_incomplete(input: &str) -> bool {
+    let mut depth: i64 = 0;
+    for token in Lexer::new(input) {
+        match token {
+            Ok(token) => match token.token {
+                Token::LeftBracket | Token::LeftParen => depth += 1,
+                Token::RightBracket | Token::RightParen => depth -= 1,
+                _ => {}
+            },
+            // Other errors are left to be reported when the entry is run
+            Err(error) if error.is_unterminated() => return true,
+            Err(_) => {}
+        }
+    }
+
+    // Negative depth is a syntax error so is left for the parser to report
+    depth > 0
+}
+
+/// Checks if the entry ends with an if, which an else on the next line would carry on
+fn awaits_else(input: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut in_if = false;
+    for token in Lexer::new(input).flatten() {
+        match token.token {
+            Token::LeftBracket | Token::LeftParen => depth += 1,
+            Token::RightBracket | Token::RightParen => depth -= 1,
+            Token::If | Token::Elseif if depth == 0 => in_if = true,
+            // Nothing can come after an else, and anything else at the top level starts a new statement
+            Token::Eof | Token::DocComment(_) => {},
+            _ if depth == 0 => in_if = false,
+            _ => {},
+        }
+    }
+
+    depth == 0 && in_if
+}
+
+/// Reads entries from stdin until the end of input is reached
+pub fn run(options: Options) -> io::Result<()> {
+    let mut repl = Repl::with_options(options);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("{}", if repl.is_continuing() { CONTINUE_PROMPT } else { PROMPT });
+        io::stdout().flush()?;
+
+        match lines.next() {
+            Some(line) => {
+                let result = repl.feed(&line?);
+                report(&repl, result);
+            },
+            None => {
+                let result = repl.finish();
+                report(&repl, result);
+                break;
+            },
+        }
+    }
+
+    println!();
+    Ok(())
+}
+
+/// Prints the values given back by an entry, or the errors it ran into
+fn report(repl: &Repl, result: Option<Result<Vec<Value>, Error>>) {
+    match result {
+        Some(Ok(values)) => {
+            for value in values {
+                println!("{value}");
+            }
+        },
+        Some(Err(error)) => {
+            for diagnostic in error.diagnostics() {
+                diagnostic::report(&diagnostic, "<repl>", &repl.last_entry);
+            }
+        },
+        None => {}
+    }
+}
@@ -0,0 +1,99 @@
+use super::*;
+
+#[test]
+fn test_echo_expression() {
+    let mut repl = Repl::default();
+
+    assert!(matches!(
+        repl.feed("3 + 4;"),
+        Some(Ok(values)) if values == vec![Value::Int(7)]
+    ));
+}
+
+#[test]
+fn test_unclosed_bracket_continues() {
+    let mut repl = Repl::default();
+
+    assert!(repl.feed("repeat(2) {").is_none());
+    assert!(repl.feed("print(\"abc\")").is_none());
+    assert!(matches!(repl.feed("}"), Some(Ok(_))));
+    assert!(!repl.is_continuing());
+}
+
+#[test]
+fn test_unterminated_string_continues() {
+    assert!(is_incomplete("\"abc"));
+    assert!(!is_incomplete("\"a{c\";"));
+}
+
+#[test]
+fn test_comments_ignored_when_continuing() {
+    assert!(!is_incomplete("print(\"a\"); # unclosed { in a comment"));
+    assert!(is_incomplete("#* block comment"));
+    assert!(!is_incomplete("#* { *# 3;"));
+}
+
+#[test]
+fn test_echo_negative_numbers() {
+    let mut repl = Repl::default();
+
+    assert!(matches!(
+        repl.feed("-5; -2.5;"),
+        Some(Ok(values)) if values == vec![Value::Int(-5), Value::Float(-2.5)]
+    ));
+}
+
+#[test]
+fn test_variables_kept_between_entries() {
+    let mut repl = Repl::default();
+
+    assert!(matches!(repl.feed("x = 2;"), Some(Ok(values)) if values.is_empty()));
+    assert!(matches!(
+        repl.feed("x * 3;"),
+        Some(Ok(values)) if values == vec![Value::Int(6)]
+    ));
+    assert!(matches!(repl.feed("y;"), Some(Err(Error::Runtime(_)))));
+}
+
+#[test]
+fn test_functions_kept_between_entries() {
+    let mut repl = Repl::default();
+
+    assert!(matches!(repl.feed("fn double(x) { return x * 2; }"), Some(Ok(values)) if values.is_empty()));
+    assert!(matches!(
+        repl.feed("double(21);"),
+        Some(Ok(values)) if values == vec![Value::Int(42)]
+    ));
+}
+
+#[test]
+fn test_else_on_next_line() {
+    let mut repl = Repl::default();
+
+    assert!(repl.feed("x = 0;").is_some());
+    assert!(repl.feed("if (x) { y = 1; }").is_none());
+    assert!(repl.feed("else if (false) { y = 2; }").is_none());
+    assert!(repl.feed("else { y = 3; }").is_some());
+    assert!(matches!(repl.feed("y;"), Some(Ok(values)) if values == vec![Value::Int(3)]));
+
+    // Anything other than else is run along with the if
+    assert!(repl.feed("if (true) { y = 4; }").is_none());
+    assert!(matches!(repl.feed("y;"), Some(Ok(values)) if values == vec![Value::Int(4)]));
+
+    // A blank line or the end of input runs the if straight away
+    assert!(repl.feed("if (true) { y = 5; }").is_none());
+    assert!(matches!(repl.feed(""), Some(Ok(_))));
+    assert!(repl.feed("if (true) { y = 6; }").is_none());
+    assert!(matches!(repl.finish(), Some(Ok(_))));
+    assert!(matches!(repl.feed("y;"), Some(Ok(values)) if values == vec![Value::Int(6)]));
+}
+
+#[test]
+fn test_functions_hoisted_within_an_entry() {
+    let mut repl = Repl::default();
+
+    assert!(matches!(
+        repl.feed("f(); fn f() { return 1; }"),
+        Some(Ok(values)) if values == vec![Value::Int(1)]
+    ));
+}
@@ -4,11 +4,192 @@ use super::*;
 
 #[test]
 fn test_print() {
-    let tokens = lexer::tokenizer("print(\"abc\")".to_string());
+    let tokens = lexer::tokenizer("print(\"abc\")".to_string()).unwrap();
 
     let instructions = parser::parse(tokens).unwrap();
     
     let mut variables: Vec<interpreter::Variable> = Vec::new();
     let mut functions: Vec<interpreter::Function> = Vec::new();
     interpreter::interpret(instructions, &mut variables, &mut functions).unwrap();
-}
\ No newline at end of file
+}
+#[test]
+fn test_unclosed_print_is_error() {
+    assert!(matches!(
+        cli::run_source("print(".to_string(), &cli::Options::default()),
+        Err(cli::Error::Syntax(_))
+    ));
+}
+
+#[test]
+fn test_parse_run_args() {
+    let args = vec!["run".to_string(), "examples/test.mahou".to_string()];
+
+    assert_eq!(
+        cli::parse_args(&args),
+        Ok(cli::Command::Run {
+            path: "examples/test.mahou".to_string(),
+            options: cli::Options::default()
+        })
+    );
+}
+
+#[test]
+fn test_parse_debug_flags() {
+    let args = vec!["run".to_string(), "--dump-ast".to_string(), "a.mahou".to_string(), "--trace".to_string()];
+
+    assert_eq!(
+        cli::parse_args(&args),
+        Ok(cli::Command::Run {
+            path: "a.mahou".to_string(),
+            options: cli::Options { dump_tokens: false, dump_ast: true, trace: true, ..Default::default() }
+        })
+    );
+}
+
+#[test]
+fn test_check_reports_every_error() {
+    let errors = cli::check_source("print(1 +);\nprint(\"ok\");\nprint(2 2);".to_string(), &cli::Options::default());
+
+    let lines: Vec<u64> = errors.iter().map(|error| error.span.line).collect();
+    assert_eq!(lines, vec![1, 3]);
+}
+
+#[test]
+fn test_check_skips_the_rest_of_a_block_after_an_error() {
+    let source = "if (x) {\n  y = 1 +;\n}\nelse {\n  y = 2;\n}\nwhile (true) {\n  if (y) { z = ); }\n  z = 2;\n}\nprint(1 2);";
+    let errors = cli::check_source(source.to_string(), &cli::Options::default());
+
+    let lines: Vec<u64> = errors.iter().map(|error| error.span.line).collect();
+    assert_eq!(lines, vec![2, 8, 11]);
+}
+
+#[test]
+fn test_check_keeps_checking_after_an_error() {
+    let codes = |source: &str| -> Vec<(u64, Option<&str>)> {
+        let errors = cli::check_source(source.to_string(), &cli::Options::default());
+        errors.iter().map(|error| (error.span.line, error.code)).collect()
+    };
+
+    assert_eq!(codes("fn main() { }\nprintln(1);\nx = (;"), vec![(2, Some("E0214")), (3, Some("E0210"))]);
+    assert_eq!(codes("break;\nx = (;\ncontinue;"), vec![(1, Some("E0211")), (2, Some("E0210")), (3, Some("E0211"))]);
+}
+
+#[test]
+fn test_parse_keywords_flag() {
+    let args = vec!["check".to_string(), "--keywords=japanese".to_string(), "a.mahou".to_string()];
+
+    assert_eq!(
+        cli::parse_args(&args),
+        Ok(cli::Command::Check {
+            path: "a.mahou".to_string(),
+            options: cli::Options { keywords: lexer::Keywords::Japanese, ..Default::default() }
+        })
+    );
+    assert!(cli::parse_args(&["run".to_string(), "--keywords=french".to_string()]).is_err());
+}
+
+#[test]
+fn test_parse_args_errors() {
+    let args = |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+
+    assert_eq!(cli::parse_args(&args(&["run"])), Err(messages::Message::MissingPath("run")));
+    assert_eq!(cli::parse_args(&args(&["check", "a.mahou", "--nope"])), Err(messages::Message::UnknownFlag("--nope".to_string())));
+    assert_eq!(cli::parse_args(&args(&["repl", "a.mahou"])), Err(messages::Message::UnexpectedArgument("a.mahou".to_string())));
+    assert_eq!(cli::parse_args(&args(&["run", "--lang=fr", "a.mahou"])), Err(messages::Message::UnknownLang("fr".to_string())));
+    assert_eq!(cli::parse_args(&args(&["build"])), Err(messages::Message::UnknownCommand("build".to_string())));
+}
+
+#[test]
+fn test_read_file_error() {
+    let error = cli::read_file("examples/missing.mahou").unwrap_err();
+
+    let diagnostics = error.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, Some("E0401"));
+}
+
+#[test]
+fn test_expression_statements_run() {
+    assert!(cli::run_source("x = 1 + 2;\nx * 2;\n\"a\" + \"b\";".to_string(), &cli::Options::default()).is_ok());
+    assert!(matches!(
+        cli::run_source("x = 1;\nx + true;".to_string(), &cli::Options::default()),
+        Err(cli::Error::Runtime(_))
+    ));
+}
+
+#[test]
+fn test_example_runs() {
+    let source = cli::read_file("examples/test.mahou").unwrap();
+
+    assert!(cli::run_source(source, &cli::Options::default()).is_ok());
+}
+
+#[test]
+fn test_explicit_main_runs() {
+    let source = "fn main() {\n  greet(\"world\");\n}\nfn greet(name) {\n  x = \"hello \" + name;\n}";
+
+    assert!(cli::run_source(source.to_string(), &cli::Options::default()).is_ok());
+    assert!(matches!(
+        cli::run_source("fn main() { undefined(); }".to_string(), &cli::Options::default()),
+        Err(cli::Error::Runtime(_))
+    ));
+}
+
+#[test]
+fn test_check_reports_lex_and_syntax_errors() {
+    let errors = cli::check_source("x = 0x;\nz = 1 +;\nw = @;".to_string(), &cli::Options::default());
+
+    let codes: Vec<(u64, Option<&str>)> = errors.iter().map(|error| (error.span.line, error.code)).collect();
+    assert!(codes.contains(&(1, Some("E0105"))));
+    assert!(codes.contains(&(2, Some("E0210"))));
+    assert!(codes.contains(&(3, Some("E0107"))));
+    assert!(codes.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+}
+
+#[test]
+fn test_check_reports_duplicate_functions() {
+    let errors = cli::check_source("fn f() { }\nfn f() { }\nfn f(x) { }".to_string(), &cli::Options::default());
+
+    let codes: Vec<(u64, Option<&str>)> = errors.iter().map(|error| (error.span.line, error.code)).collect();
+    assert_eq!(codes, vec![(2, Some("E0215")), (3, Some("E0215"))]);
+}
+
+#[test]
+fn test_check_reports_code_outside_main() {
+    let errors = cli::check_source("fn main() { }\nx = 1;".to_string(), &cli::Options::default());
+
+    let codes: Vec<(u64, Option<&str>)> = errors.iter().map(|error| (error.span.line, error.code)).collect();
+    assert_eq!(codes, vec![(2, Some("E0214"))]);
+}
+
+#[test]
+fn test_run_reader() {
+    let source = "x = 1;\nprintln(x);\ny = x + true;\n";
+
+    let (result, read) = cli::run_reader(source.as_bytes(), &cli::Options::default());
+    assert!(matches!(result, Err(cli::Error::Runtime(_))));
+    assert_eq!(read, cli::SourceWindow { start: 0, text: source.to_string() });
+    assert!(cli::run_reader("println(1);".as_bytes(), &cli::Options::default()).0.is_ok());
+}
+
+#[test]
+fn test_run_reader_keeps_only_recent_lines() {
+    let source = format!("{}y = x + true;\nz = 1;\n", "x = 1;\n".repeat(1000));
+
+    let (result, read) = cli::run_reader(source.as_bytes(), &cli::Options::default());
+    assert_eq!(read.text.lines().count(), 100);
+    assert!(source.ends_with(&read.text));
+    assert_eq!(read.start, source.len() - read.text.len());
+
+    let Err(cli::Error::Runtime(error)) = result else { panic!("expected a runtime error") };
+    assert!(read.text[error.span().start - read.start..].starts_with("x + true"));
+}
+
+#[test]
+fn test_syntax_error_not_hidden_by_later_lex_error() {
+    let result = cli::parse_source("x = (1 + ;\ny = \"never closed", &cli::Options::default());
+    assert!(matches!(result, Err(cli::Error::Syntax(error)) if error.span().line == 1));
+
+    let result = cli::parse_source("x = @;\ny = (1 + ;", &cli::Options::default());
+    assert!(matches!(result, Err(cli::Error::Lex(errors)) if errors.len() == 1));
+}